@@ -1,7 +1,10 @@
 //! Example of using the Adler-32 checksum and a simple visualization
 //! of it's weaknesses with small message sizes.
 //!
-//! This also performs a Kolmogorov–Smirnov test on the data
+//! This also performs Kolmogorov–Smirnov, Anderson–Darling,
+//! Cramér–von Mises and chi-square tests on the data, including a
+//! 16-bit Fletcher-16 run, and measures how much of each output space
+//! is used
 #[cfg(feature = "external-rand")]
 use tapestry_analysis::analysis::rand_distribution::RandDiscreteUniformDistribution;
 
@@ -9,43 +12,50 @@ use tapestry_analysis::analysis::rand_distribution::RandDiscreteUniformDistribut
 use tapestry_analysis::analysis::distribution::DiscreteUniformDistribution;
 
 use tapestry_analysis::analysis::{
-    distribution::{CriticalValue, DiscreteUniformDistributionParameters},
-    experiment::Experiment,
+    anderson_darling, bit_bias,
+    chi_square::{self, uniform_probabilities},
+    coverage, cramer_von_mises,
+    distribution::CriticalValue,
+    experiment::{Experiment, OutputWidth},
     histogram::{Histogram, SimpleHistogram},
-    ks::{critical_value, statistic},
-    sample::Sample,
+    ks::{cdf_statistic, critical_value, p_value, two_sample_test},
+    message::SparseMessages,
 };
 
 use checksum_tapestry::adler32::Adler32;
 use checksum_tapestry::crc::{BitOrder, BitWidth, CRCConfiguration, CRC};
+use checksum_tapestry::fletcher16::Fletcher16;
 
 const NUM_EXPERIMENTS: u32 = 1000;
 const MESSAGE_SIZE: u32 = 50;
 const NUM_BINS: u8 = 10;
 
 /// Perform a Kolmogorov–Smirnov test on an experiment
-fn perform_ks_test(name: &str, experiment: &Experiment<u32>) {
-    let experiment_f32: Experiment<f32> = Experiment {
-        samples: experiment
-            .samples
-            .iter()
-            .map(|s| Sample {
-                sample: (*s).sample as f32,
-            })
-            .collect(),
-    };
-
-    let parameters = DiscreteUniformDistributionParameters { a: 0, b: u32::MAX };
-    let statistic = statistic(experiment_f32, &parameters);
+///
+/// The experiment is compared to the uniform distribution over every
+/// output of its width.
+fn perform_ks_test<T: OutputWidth>(name: &str, experiment: &Experiment<T>) {
+    let statistic = cdf_statistic(experiment.to_f32(), &experiment.uniform_parameters());
 
     let n: u32 = experiment.samples.len().try_into().unwrap();
     let crit_val = critical_value(CriticalValue::FivePercent, n);
+    let p = p_value(statistic, n);
     if let Some(cv) = crit_val {
         println!(
-            "{0:>1$}{2:>3$}{4:>5$}     reject the null hypothesis?",
-            "name", 8, "statistic", 15, "critical value", 18
+            "{0:>1$}{2:>3$}{4:>5$}{6:>7$}     reject the null hypothesis?",
+            "name", 8, "statistic", 15, "critical value", 18, "p-value", 15
+        );
+        print!(
+            "{0:>1$}{2:>3$}{4:>5$}{6:>7$}     ",
+            name,
+            8,
+            statistic,
+            15,
+            cv,
+            18,
+            format!("{:.3e}", p),
+            15
         );
-        print!("{0:>1$}{2:>3$}{4:>5$}     ", name, 8, statistic, 15, cv, 18);
         if statistic < cv {
             println!("no,  data follows a uniform distribution");
         } else {
@@ -56,6 +66,78 @@ fn perform_ks_test(name: &str, experiment: &Experiment<u32>) {
     }
 }
 
+/// Perform the Anderson–Darling and Cramér–von Mises tests on an experiment
+fn perform_edf_tests<T: OutputWidth>(name: &str, experiment: &Experiment<T>) {
+    let experiment_f32 = experiment.to_f32();
+    let parameters = experiment.uniform_parameters();
+
+    if let (Some(ad), Some(cvm)) = (
+        anderson_darling::test(&experiment_f32, &parameters),
+        cramer_von_mises::test(&experiment_f32, &parameters),
+    ) {
+        println!(
+            "{}: Anderson–Darling {} (p-value {:.3e}), Cramér–von Mises {} (p-value {:.3e})",
+            name, ad.statistic, ad.p_value, cvm.statistic, cvm.p_value
+        );
+    }
+}
+
+/// Perform a chi-square test on the binned data of an experiment
+fn perform_chi_square_test(name: &str, histogram: &SimpleHistogram) {
+    let result = chi_square::test(histogram, &uniform_probabilities(histogram.num_bins));
+    if let Some(result) = result {
+        println!(
+            "{0:>1$}{2:>3$}{4:>5$}{6:>7$}     reject the null hypothesis?",
+            "name", 8, "statistic", 15, "df", 5, "p-value", 15
+        );
+        print!(
+            "{0:>1$}{2:>3$}{4:>5$}{6:>7$}     ",
+            name,
+            8,
+            result.statistic,
+            15,
+            result.degrees_of_freedom,
+            5,
+            format!("{:.3e}", result.p_value),
+            15
+        );
+        if !result.reject(CriticalValue::FivePercent) {
+            println!("no,  data follows a uniform distribution");
+        } else {
+            println!("yes, data does not follow a uniform distribution");
+        }
+    } else {
+        println!("Invalid histogram");
+    }
+}
+
+/// Report output bits that aren't set half of the time
+fn perform_bit_bias_test<T: OutputWidth>(name: &str, experiment: &Experiment<T>) {
+    if let Some(result) = bit_bias::test(experiment) {
+        println!(
+            "{}: p-value {:.3e}, biased bits {:?}",
+            name,
+            result.p_value,
+            result.biased_bits(CriticalValue::FivePercent)
+        );
+    }
+}
+
+/// Report how much of the output space an experiment used
+fn perform_coverage_test<T: OutputWidth>(name: &str, experiment: &Experiment<T>) {
+    if let Some(result) = coverage::test(experiment) {
+        println!(
+            "{}: {} distinct outputs ({:.1} expected), {} collisions ({:.1} expected), p-value {:.3e}",
+            name,
+            result.distinct,
+            result.expected_distinct,
+            result.collisions,
+            result.expected_collisions,
+            result.p_value
+        );
+    }
+}
+
 fn main() {
     #[cfg(not(feature = "external-rand"))]
     let mut dud = DiscreteUniformDistribution::new(0, u32::MAX);
@@ -67,9 +149,13 @@ fn main() {
     let adler32_experiment =
         Experiment::<u32>::run(&mut dud, &mut adler32, MESSAGE_SIZE, NUM_EXPERIMENTS);
 
+    if let Some(seed) = adler32_experiment.seed {
+        println!("Message distribution seed: {seed}");
+    }
+
     println!("Adler32 Histogram");
-    let histogram = SimpleHistogram::new(&adler32_experiment, NUM_BINS);
-    histogram.draw_terminal();
+    let adler32_histogram = SimpleHistogram::new(&adler32_experiment, NUM_BINS);
+    adler32_histogram.draw_terminal();
 
     println!();
 
@@ -90,11 +176,79 @@ fn main() {
         Experiment::<u32>::run(&mut dud, &mut crc32, MESSAGE_SIZE, NUM_EXPERIMENTS);
 
     println!("CRC32 Histogram");
-    let histogram = SimpleHistogram::new(&crc_experiment, NUM_BINS);
-    histogram.draw_terminal();
+    let crc_histogram = SimpleHistogram::new(&crc_experiment, NUM_BINS);
+    crc_histogram.draw_terminal();
+
+    println!();
+
+    // Run a Fletcher-16 experiment, the histogram covers the 16-bit
+    // output space
+    let mut fletcher16 = Fletcher16::default();
+    let fletcher16_experiment =
+        Experiment::<u16>::run(&mut dud, &mut fletcher16, MESSAGE_SIZE, NUM_EXPERIMENTS);
+
+    println!("Fletcher16 Histogram");
+    let fletcher16_histogram = SimpleHistogram::new(&fletcher16_experiment, NUM_BINS);
+    fletcher16_histogram.draw_terminal();
 
     println!();
 
     perform_ks_test("Adler32", &adler32_experiment);
     perform_ks_test("CRC32", &crc_experiment);
+    perform_ks_test("Fletch16", &fletcher16_experiment);
+
+    println!();
+
+    perform_edf_tests("Adler32", &adler32_experiment);
+    perform_edf_tests("CRC32", &crc_experiment);
+    perform_edf_tests("Fletcher16", &fletcher16_experiment);
+
+    println!();
+
+    perform_chi_square_test("Adler32", &adler32_histogram);
+    perform_chi_square_test("CRC32", &crc_histogram);
+    perform_chi_square_test("Fletch16", &fletcher16_histogram);
+
+    println!();
+
+    perform_bit_bias_test("Adler32", &adler32_experiment);
+    perform_bit_bias_test("CRC32", &crc_experiment);
+    perform_bit_bias_test("Fletcher16", &fletcher16_experiment);
+
+    println!();
+
+    perform_coverage_test("Adler32", &adler32_experiment);
+    perform_coverage_test("CRC32", &crc_experiment);
+    perform_coverage_test("Fletcher16", &fletcher16_experiment);
+
+    // Every two byte message, the coverage here is exact
+    let adler32_exhaustive = Experiment::<u32>::run_exhaustive(&mut adler32, 2);
+    let fletcher16_exhaustive = Experiment::<u16>::run_exhaustive(&mut fletcher16, 2);
+    perform_coverage_test("Adler32, all 2 byte messages", &adler32_exhaustive);
+    perform_coverage_test("Fletcher16, all 2 byte messages", &fletcher16_exhaustive);
+
+    println!();
+
+    // Compare the two checksums directly, without assuming a distribution
+    if let Some(result) = two_sample_test(&adler32_experiment, &crc_experiment) {
+        println!(
+            "Two-sample Adler32 vs CRC32: statistic {}, p-value {:.3e}",
+            result.statistic, result.p_value
+        );
+    }
+
+    println!();
+
+    // Structured data: messages where one byte in twenty is non-zero
+    // About 8% of these messages are all zeros, so even CRC32 shows a
+    // spike at the checksum of the zero message
+    let mut sparse = SparseMessages::new(&mut dud, MESSAGE_SIZE.try_into().unwrap(), 0.05);
+    let adler32_sparse = Experiment::<u32>::run_source(&mut sparse, &mut adler32, NUM_EXPERIMENTS);
+    let crc_sparse = Experiment::<u32>::run_source(&mut sparse, &mut crc32, NUM_EXPERIMENTS);
+
+    println!("Sparse messages");
+    perform_ks_test("Adler32", &adler32_sparse);
+    perform_ks_test("CRC32", &crc_sparse);
+    perform_coverage_test("Adler32", &adler32_sparse);
+    perform_coverage_test("CRC32", &crc_sparse);
 }
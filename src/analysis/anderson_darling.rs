@@ -0,0 +1,290 @@
+//! Anderson–Darling test
+//! Perform an Anderson–Darling goodness of fit test on a data set
+//! This can be used to find if a set of values comes from a given
+//! distribution.
+//! The Anderson–Darling statistic weights differences in the tails
+//! of the distribution more heavily than Kolmogorov–Smirnov.  Bias in
+//! checksums often shows up in the tails, like Adler-32 never
+//! reaching high values for short messages.
+//!
+//! The p-values are for a fully specified distribution, where no
+//! parameters were estimated from the data.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::{distribution::Cdf, experiment::Experiment};
+
+/// CDF values are clamped to [EPSILON, 1 - EPSILON] so samples on the
+/// edge of the support don't take the log of zero
+const EPSILON: f64 = 1.0e-10;
+
+/// The result of an Anderson–Darling test
+#[derive(Debug)]
+pub struct AndersonDarlingTest {
+    /// The Anderson–Darling A^2 statistic
+    pub statistic: f32,
+    /// The probability of seeing a statistic at least this large if
+    /// the data follows the expected distribution
+    pub p_value: f32,
+}
+
+/// Calculate the Anderson–Darling A^2 statistic
+///
+/// A^2 = -n - 1/n * sum_{i=1}^{n} (2i - 1) (ln F(x_i) + ln(1 - F(x_{n+1-i})))
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{
+///     anderson_darling::statistic,
+///     distribution::DiscreteUniformDistributionParameters,
+///     experiment::Experiment,
+///     sample::Sample,
+/// };
+///
+/// let data: [f32; 8] = [1.41, 0.26, 1.97, 0.33, 0.55, 0.77, 1.46, 1.18];
+///
+/// let samples: Vec<Sample<f32>> = data.iter().map(|d| Sample { sample: *d }).collect();
+///
+/// let experiment: Experiment<f32> = Experiment::from_samples(samples);
+/// let parameters = DiscreteUniformDistributionParameters { a: 0, b: 2 };
+/// let statistic = statistic(&experiment, &parameters);
+/// assert!(f32::abs(statistic - 0.2891) < 0.0001);
+/// ```
+pub fn statistic<C: Cdf + ?Sized>(experiment: &Experiment<f32>, cdf: &C) -> f32 {
+    let mut u: Vec<f64> = experiment
+        .samples
+        .iter()
+        .map(|s| (cdf.cdf(s.sample) as f64).clamp(EPSILON, 1.0 - EPSILON))
+        .collect();
+    u.sort_by(|a, b| a.total_cmp(b));
+
+    let n = u.len();
+    let mut sum: f64 = 0.0;
+    for i in 0..n {
+        let weight = (2 * i + 1) as f64;
+        sum += weight * (f64::ln(u[i]) + f64::ln(1.0 - u[n - 1 - i]));
+    }
+
+    (-(n as f64) - sum / n as f64) as f32
+}
+
+/// Compute the p-value for an Anderson–Darling statistic with n
+/// samples
+///
+/// This uses the asymptotic distribution with the finite sample
+/// correction from Marsaglia and Marsaglia, which is accurate to
+/// around six digits for n >= 5.  The accuracy is absolute, so
+/// p-values below around 1e-6 only say the fit is very poor.
+///
+/// Marsaglia, G., & Marsaglia, J. (2004).
+/// Evaluating the Anderson-Darling Distribution.
+/// Journal of Statistical Software, 9(2), 1–5.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::anderson_darling::p_value;
+///
+/// // The asymptotic five percent point is 2.492
+/// let p = p_value(2.492, 1000);
+/// assert!(f32::abs(p - 0.05) < 0.001);
+/// ```
+pub fn p_value(statistic: f32, n: u32) -> f32 {
+    if n == 0 {
+        return 1.0;
+    }
+
+    let z = statistic as f64;
+    let cdf = asymptotic_cdf(z);
+    let mut correction = error_correction(n as f64, cdf);
+
+    // The correction polynomial doesn't reach zero at a CDF of one,
+    // which would put a floor of around 0.0006 / n under the
+    // p-value.  Fade it out in the far tail instead.
+    if cdf > 0.999 {
+        correction *= (1.0 - cdf) / 0.001;
+    }
+
+    (asymptotic_sf(z) - correction).clamp(0.0, 1.0) as f32
+}
+
+/// The limiting CDF of A^2 as n goes to infinity
+fn asymptotic_cdf(z: f64) -> f64 {
+    if z <= 0.0 {
+        return 0.0;
+    }
+
+    if z < 2.0 {
+        f64::exp(-1.233_714_1 / z) / f64::sqrt(z)
+            * (2.000_12
+                + (0.247_105
+                    - (0.064_982_1 - (0.034_796_2 - (0.011_672 - 0.001_686_91 * z) * z) * z) * z)
+                    * z)
+    } else {
+        f64::exp(-f64::exp(
+            1.077_6
+                - (2.306_95 - (0.434_24 - (0.082_433 - (0.008_056 - 0.000_314_6 * z) * z) * z) * z)
+                    * z,
+        ))
+    }
+}
+
+/// The upper tail of the limiting distribution, 1 - asymptotic_cdf(z)
+///
+/// For large z this is computed directly so small p-values aren't
+/// lost to rounding.
+fn asymptotic_sf(z: f64) -> f64 {
+    if z < 2.0 {
+        1.0 - asymptotic_cdf(z)
+    } else {
+        -f64::exp_m1(-f64::exp(
+            1.077_6
+                - (2.306_95 - (0.434_24 - (0.082_433 - (0.008_056 - 0.000_314_6 * z) * z) * z) * z)
+                    * z,
+        ))
+    }
+}
+
+/// The finite sample correction to the asymptotic CDF value x
+fn error_correction(n: f64, x: f64) -> f64 {
+    if x > 0.8 {
+        return (-130.213_7
+            + (745.233_7 - (1_705.091 - (1_950.646 - (1_116.360 - 255.784_4 * x) * x) * x) * x)
+                * x)
+            / n;
+    }
+
+    let c = 0.012_65 + 0.175_7 / n;
+    if x < c {
+        let t = x / c;
+        let t = f64::sqrt(t) * (1.0 - t) * (49.0 * t - 102.0);
+        return t * (0.003_7 / (n * n) + 0.000_78 / n + 0.000_06) / n;
+    }
+
+    let t = (x - c) / (0.8 - c);
+    let t = -0.000_226_33
+        + (1.545_34 - (3.636_2 - (3.682_82 - (1.395_84 - 0.072_12 * t) * t) * t) * t) * t;
+    t * (0.042_13 / n + 0.013_65 / (n * n))
+}
+
+/// Perform an Anderson–Darling test on an experiment
+///
+/// Returns None if the experiment is empty.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{
+///     anderson_darling::test,
+///     distribution::{DiscreteUniformDistribution, DiscreteUniformDistributionParameters},
+///     experiment::Experiment,
+///     sample::Sample,
+/// };
+/// use checksum_tapestry::adler32::Adler32;
+///
+/// let mut dud = DiscreteUniformDistribution::new(0, u32::MAX);
+///
+/// // Short messages only use the bottom of the Adler-32 output space
+/// let mut adler32 = Adler32::default();
+/// let experiment = Experiment::<u32>::run(&mut dud, &mut adler32, 50, 1000);
+/// let experiment: Experiment<f32> = Experiment::from_samples(
+///     experiment
+///         .samples
+///         .iter()
+///         .map(|s| Sample {
+///             sample: s.sample as f32,
+///         })
+///         .collect(),
+/// );
+///
+/// let parameters = DiscreteUniformDistributionParameters { a: 0, b: u32::MAX };
+/// let result = test(&experiment, &parameters).unwrap();
+/// assert!(result.p_value < 0.01);
+/// ```
+pub fn test<C: Cdf + ?Sized>(experiment: &Experiment<f32>, cdf: &C) -> Option<AndersonDarlingTest> {
+    if experiment.samples.is_empty() {
+        return None;
+    }
+
+    let statistic = statistic(experiment, cdf);
+    let n: u32 = experiment.samples.len().try_into().ok()?;
+
+    Some(AndersonDarlingTest {
+        statistic,
+        p_value: p_value(statistic, n),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        anderson_darling::{p_value, statistic, test},
+        distribution::DiscreteUniformDistributionParameters,
+        experiment::Experiment,
+        sample::Sample,
+    };
+
+    /// PennState STAT 415 Introduction to Mathematical Statistics
+    /// Kolmogorov–Smirnov example data, tested with Anderson–Darling
+    #[test]
+    fn statistic_works_psu() {
+        let data: [f32; 8] = [1.41, 0.26, 1.97, 0.33, 0.55, 0.77, 1.46, 1.18];
+
+        let samples: Vec<Sample<f32>> = data.iter().map(|d| Sample { sample: *d }).collect();
+
+        let experiment: Experiment<f32> = Experiment::from_samples(samples);
+        let parameters = DiscreteUniformDistributionParameters { a: 0, b: 2 };
+
+        let statistic = statistic(&experiment, &parameters);
+        assert!(f32::abs(statistic - 0.2891) < 0.0001);
+
+        let result = test(&experiment, &parameters).unwrap();
+        assert!(f32::abs(result.p_value - 0.9459) < 0.0001);
+    }
+
+    /// Test p-values against the Marsaglia and Marsaglia reference
+    /// implementation
+    #[test]
+    fn p_value_works() {
+        assert!(f32::abs(p_value(2.492, 10) - 0.051_244) < 0.00001);
+        assert!(f32::abs(p_value(1.0, 5) - 0.355_083) < 0.00001);
+        assert!(f32::abs(p_value(0.5, 100) - 0.746_723) < 0.00001);
+        assert!(f32::abs(p_value(6.0, 20) - 0.001_018) < 0.00001);
+    }
+
+    /// Large statistics give small p-values instead of stopping at the
+    /// floor of the finite sample correction
+    #[test]
+    fn p_value_tail_works() {
+        let p = p_value(15.0, 1000);
+        assert!(p > 0.0);
+        assert!(p < 1.0e-8);
+        assert!(p_value(20.0, 1000) < p);
+    }
+
+    /// Samples on the edge of the support give a large but finite
+    /// statistic
+    #[test]
+    fn statistic_edge_works() {
+        let data: [f32; 4] = [0.0, 1.0, 1.0, 2.0];
+
+        let samples: Vec<Sample<f32>> = data.iter().map(|d| Sample { sample: *d }).collect();
+
+        let experiment: Experiment<f32> = Experiment::from_samples(samples);
+        let parameters = DiscreteUniformDistributionParameters { a: 0, b: 2 };
+
+        let statistic = statistic(&experiment, &parameters);
+        assert!(statistic.is_finite());
+        assert!(p_value(statistic, 4) < 0.01);
+    }
+
+    /// Empty experiments can't be tested
+    #[test]
+    fn test_empty_fails() {
+        let experiment: Experiment<f32> = Experiment::from_samples(vec![]);
+        let parameters = DiscreteUniformDistributionParameters { a: 0, b: 2 };
+
+        assert!(test(&experiment, &parameters).is_none());
+    }
+}
@@ -0,0 +1,238 @@
+//! Avalanche effect and strict avalanche criterion analysis
+//! Measure how flipping a single input bit changes the output bits
+//! of a checksum.
+//!
+//! A function meets the strict avalanche criterion (SAC) when
+//! flipping any single input bit flips each output bit with
+//! probability one half.  CRCs are linear, so the output difference
+//! for a flipped input bit doesn't depend on the rest of the message
+//! and every flip probability is either zero or one.  Sums like
+//! Adler-32 mostly change the low bits of each half.
+//!
+//! Webster, A. F., & Tavares, S. E. (1986). On the Design of S-Boxes.
+//! Advances in Cryptology — CRYPTO '85.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::{experiment::OutputWidth, message::MessageSource};
+use checksum_tapestry::Checksum;
+
+/// The result of an avalanche analysis
+///
+/// Input bit i is bit i % 8 of byte i / 8 of the message, counting
+/// from the least significant bit.  Output bit j is bit j of the
+/// checksum, counting from the least significant bit.
+#[derive(Debug)]
+pub struct AvalancheTest {
+    /// The number of messages each input bit was flipped in
+    ///
+    /// This is the same for every input bit unless the messages have
+    /// different lengths.
+    pub trials: Vec<u64>,
+    /// The probability that flipping input bit i flips output bit j,
+    /// indexed as flip_probabilities\[i\]\[j\]
+    pub flip_probabilities: Vec<Vec<f32>>,
+    /// The flip probability minus one half for each cell
+    pub sac_deviation: Vec<Vec<f32>>,
+    /// The largest absolute deviation from one half of any cell
+    pub max_sac_deviation: f32,
+    /// The mean absolute deviation from one half over every cell
+    pub mean_sac_deviation: f32,
+    /// The number of single bit flips that changed the output in
+    /// exactly k bits, indexed by k from 0 to the output width
+    pub hamming_distances: Vec<u64>,
+    /// The mean number of output bits changed by a single bit flip
+    ///
+    /// This is half the output width for an ideal function.
+    pub mean_hamming_distance: f32,
+}
+
+/// Perform an avalanche analysis of a checksum
+///
+/// num_messages messages are taken from the source.  For each one,
+/// every input bit is flipped in turn and the output is compared to
+/// the output of the original message.
+///
+/// Returns None if there are no messages or every message is empty.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{avalanche::test, message::UniformMessages, prng::Pcg32};
+/// use checksum_tapestry::crc::CRC;
+///
+/// let mut source = UniformMessages::new(Pcg32::new(1), 8);
+/// let mut crc32 = CRC::<u32>::default();
+/// let result = test(&mut source, &mut crc32, 100).unwrap();
+///
+/// // A CRC is linear, so every bit either always flips or never flips
+/// assert_eq!(result.flip_probabilities.len(), 64);
+/// assert_eq!(result.max_sac_deviation, 0.5);
+/// ```
+pub fn test<T: OutputWidth>(
+    source: &mut dyn MessageSource,
+    checksum: &mut dyn Checksum<T>,
+    num_messages: u32,
+) -> Option<AvalancheTest> {
+    let output_bits = T::BITS as usize;
+    let mut trials: Vec<u64> = Vec::new();
+    let mut flips: Vec<Vec<u64>> = Vec::new();
+    let mut hamming_distances: Vec<u64> = vec![0; output_bits + 1];
+    let mut message: Vec<u8> = Vec::new();
+
+    for _ in 0..num_messages {
+        message.resize(source.next_length(), 0);
+        source.fill(&mut message);
+
+        let input_bits = message.len() * 8;
+        if input_bits > trials.len() {
+            trials.resize(input_bits, 0);
+            flips.resize(input_bits, vec![0; output_bits]);
+        }
+
+        let original = checksum.compute(&message).to_u64();
+        checksum.reset();
+
+        for i in 0..input_bits {
+            let mask = 1u8 << (i % 8);
+
+            message[i / 8] ^= mask;
+            let flipped = checksum.compute(&message).to_u64();
+            checksum.reset();
+            message[i / 8] ^= mask;
+
+            let difference = original ^ flipped;
+            for (j, count) in flips[i].iter_mut().enumerate() {
+                *count += (difference >> j) & 1;
+            }
+            hamming_distances[difference.count_ones() as usize] += 1;
+            trials[i] += 1;
+        }
+    }
+
+    if trials.is_empty() {
+        return None;
+    }
+
+    let flip_probabilities: Vec<Vec<f32>> = flips
+        .iter()
+        .zip(trials.iter())
+        .map(|(row, n)| row.iter().map(|c| (*c as f64 / *n as f64) as f32).collect())
+        .collect();
+
+    let sac_deviation: Vec<Vec<f32>> = flip_probabilities
+        .iter()
+        .map(|row| row.iter().map(|p| p - 0.5).collect())
+        .collect();
+
+    let num_cells = (sac_deviation.len() * output_bits) as f32;
+    let max_sac_deviation = sac_deviation
+        .iter()
+        .flatten()
+        .fold(0.0_f32, |max, d| f32::max(max, d.abs()));
+    let mean_sac_deviation =
+        sac_deviation.iter().flatten().map(|d| d.abs()).sum::<f32>() / num_cells;
+
+    let total_flips: u64 = hamming_distances.iter().sum();
+    let mean_hamming_distance = hamming_distances
+        .iter()
+        .enumerate()
+        .map(|(k, count)| k as f64 * *count as f64)
+        .sum::<f64>()
+        / total_flips as f64;
+
+    Some(AvalancheTest {
+        trials,
+        flip_probabilities,
+        sac_deviation,
+        max_sac_deviation,
+        mean_sac_deviation,
+        hamming_distances,
+        mean_hamming_distance: mean_hamming_distance as f32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        avalanche::test,
+        distribution::DiscreteUniformDistribution,
+        message::{UniformMessages, VariableLengthMessages},
+        prng::Pcg32,
+        test_support::Mixed,
+    };
+    use checksum_tapestry::{adler32::Adler32, crc::CRC};
+
+    /// A CRC's output difference doesn't depend on the message
+    #[test]
+    fn test_works_crc32() {
+        let mut source = UniformMessages::new(Pcg32::new(1), 4);
+        let mut crc32 = CRC::<u32>::default();
+        let result = test(&mut source, &mut crc32, 50).unwrap();
+
+        assert_eq!(result.trials, vec![50; 32]);
+        assert!(result
+            .flip_probabilities
+            .iter()
+            .flatten()
+            .all(|p| *p == 0.0 || *p == 1.0));
+        assert_eq!(result.max_sac_deviation, 0.5);
+        assert_eq!(result.mean_sac_deviation, 0.5);
+        assert_eq!(result.hamming_distances.len(), 33);
+        assert_eq!(result.hamming_distances.iter().sum::<u64>(), 50 * 32);
+        // A single bit error is always detected
+        assert_eq!(result.hamming_distances[0], 0);
+    }
+
+    /// Flipping the low bit of the last byte only changes a few low
+    /// bits of each Adler-32 sum
+    #[test]
+    fn test_works_adler32() {
+        let mut source = UniformMessages::new(Pcg32::new(2), 16);
+        let mut adler32 = Adler32::default();
+        let result = test(&mut source, &mut adler32, 200).unwrap();
+
+        let last_byte_low_bit = &result.flip_probabilities[15 * 8];
+        assert_eq!(last_byte_low_bit[31], 0.0);
+        assert_eq!(last_byte_low_bit[15], 0.0);
+        assert!(result.mean_hamming_distance < 16.0);
+    }
+
+    /// A well mixed function is close to the strict avalanche
+    /// criterion
+    #[test]
+    fn test_works_mixed() {
+        let mut source = UniformMessages::new(Pcg32::new(3), 8);
+        let mut mixed = Mixed::default();
+        let result = test(&mut source, &mut mixed, 2000).unwrap();
+
+        assert!(result.max_sac_deviation < 0.05);
+        assert!(result.mean_sac_deviation < 0.02);
+        assert!(f32::abs(result.mean_hamming_distance - 32.0) < 0.1);
+    }
+
+    /// Longer messages add rows with their own trial counts
+    #[test]
+    fn test_works_variable_length() {
+        let bytes = UniformMessages::new(Pcg32::new(4), 0);
+        let lengths = DiscreteUniformDistribution::with_seed(1, 2, 4);
+        let mut source = VariableLengthMessages::new(bytes, lengths);
+        let mut crc32 = CRC::<u32>::default();
+        let result = test(&mut source, &mut crc32, 100).unwrap();
+
+        assert_eq!(result.trials.len(), 16);
+        assert_eq!(result.trials[0], 100);
+        assert!(result.trials[8] < 100);
+    }
+
+    /// Empty messages can't be tested
+    #[test]
+    fn test_empty_fails() {
+        let mut source = UniformMessages::new(Pcg32::new(5), 0);
+        let mut crc32 = CRC::<u32>::default();
+
+        assert!(test(&mut source, &mut crc32, 10).is_none());
+        let mut source = UniformMessages::new(Pcg32::new(5), 4);
+        assert!(test(&mut source, &mut crc32, 0).is_none());
+    }
+}
@@ -0,0 +1,236 @@
+//! Per-output-bit bias test
+//! Test whether each bit of a checksum's output is one half of the
+//! time.
+//! A checksum can pass a Kolmogorov–Smirnov test on the whole value
+//! and still have individual bits that are strongly biased.  For
+//! short messages the high bits of both Adler-32 sums are almost
+//! always zero.
+//!
+//! Each bit is tested with a two-sided z-test on the proportion of
+//! ones.  Testing every bit of a 32-bit output at five percent would
+//! find a "biased" bit in most good checksums by chance, so the
+//! combined verdict uses the Holm–Bonferroni correction.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::{
+    distribution::CriticalValue,
+    experiment::{Experiment, OutputWidth},
+    special::erfc,
+};
+
+/// The bias of a single output bit
+#[derive(Debug)]
+pub struct BitBias {
+    /// The proportion of samples with this bit set
+    pub proportion: f32,
+    /// The z-score of the number of ones, positive when there are too
+    /// many ones
+    pub z_score: f32,
+    /// The two-sided p-value of this bit on its own
+    pub p_value: f32,
+    /// The p-value adjusted for testing every bit with the
+    /// Holm–Bonferroni method
+    pub adjusted_p_value: f32,
+}
+
+/// The result of a per-bit bias test
+#[derive(Debug)]
+pub struct BitBiasTest {
+    /// The bias of each bit, counting from the least significant bit
+    pub bits: Vec<BitBias>,
+    /// The probability of seeing a bias at least this large in any
+    /// bit if every bit is unbiased, the smallest adjusted p-value
+    pub p_value: f32,
+}
+
+impl BitBiasTest {
+    /// Returns true if the null hypothesis, that every bit is
+    /// unbiased, should be rejected at the given significance level
+    pub fn reject(&self, cv: CriticalValue) -> bool {
+        self.p_value < cv.alpha()
+    }
+
+    /// The bits that are biased at the given significance level,
+    /// after correcting for testing every bit
+    pub fn biased_bits(&self, cv: CriticalValue) -> Vec<usize> {
+        let alpha = cv.alpha();
+
+        self.bits
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.adjusted_p_value < alpha)
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+/// Adjust a set of p-values for multiple comparisons with the
+/// Holm–Bonferroni method
+///
+/// The smallest p-value is multiplied by m, the next by m - 1, and so
+/// on, keeping the adjusted values in the same order as the originals.
+/// This controls the chance of any false rejection like the
+/// Bonferroni correction, but rejects more often.
+///
+/// Holm, S. (1979). A Simple Sequentially Rejective Multiple Test
+/// Procedure. Scandinavian Journal of Statistics, 6(2), 65–70.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::bit_bias::holm_adjust;
+///
+/// let adjusted = holm_adjust(&[0.01, 0.04, 0.03]);
+/// let expected = [0.03, 0.06, 0.06];
+/// for (a, e) in adjusted.iter().zip(expected.iter()) {
+///     assert!(f32::abs(a - e) < 1.0e-6);
+/// }
+/// ```
+pub fn holm_adjust(p_values: &[f32]) -> Vec<f32> {
+    let m = p_values.len();
+    let mut order: Vec<usize> = (0..m).collect();
+    order.sort_by(|a, b| p_values[*a].total_cmp(&p_values[*b]));
+
+    let mut adjusted = vec![0.0; m];
+    let mut running_max: f32 = 0.0;
+    for (rank, index) in order.iter().enumerate() {
+        let value = (p_values[*index] * (m - rank) as f32).min(1.0);
+        running_max = running_max.max(value);
+        adjusted[*index] = running_max;
+    }
+
+    adjusted
+}
+
+/// Perform a per-bit bias test on an experiment
+///
+/// The number of bits tested comes from the experiment's output type.
+/// Returns None if the experiment is empty.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{
+///     bit_bias::test, distribution::CriticalValue, experiment::Experiment,
+///     message::UniformMessages, prng::Pcg32,
+/// };
+/// use checksum_tapestry::adler32::Adler32;
+///
+/// // Adler-32 of eight byte messages never sets the top bit of either sum
+/// let mut source = UniformMessages::new(Pcg32::new(1), 8);
+/// let mut adler32 = Adler32::default();
+/// let experiment = Experiment::<u32>::run_source(&mut source, &mut adler32, 1000);
+///
+/// let result = test(&experiment).unwrap();
+/// assert!(result.reject(CriticalValue::OnePercent));
+/// assert!(result.biased_bits(CriticalValue::OnePercent).contains(&31));
+/// ```
+pub fn test<T: OutputWidth>(experiment: &Experiment<T>) -> Option<BitBiasTest> {
+    if experiment.samples.is_empty() {
+        return None;
+    }
+
+    let n = experiment.samples.len() as f64;
+    let mut ones: Vec<u64> = vec![0; T::BITS as usize];
+    for sample in &experiment.samples {
+        let value = sample.sample.to_u64();
+        for (i, count) in ones.iter_mut().enumerate() {
+            *count += (value >> i) & 1;
+        }
+    }
+
+    let mut bits: Vec<BitBias> = ones
+        .iter()
+        .map(|count| {
+            let z = (*count as f64 - n / 2.0) / f64::sqrt(n / 4.0);
+            BitBias {
+                proportion: (*count as f64 / n) as f32,
+                z_score: z as f32,
+                p_value: erfc(z.abs() / std::f64::consts::SQRT_2) as f32,
+                adjusted_p_value: 1.0,
+            }
+        })
+        .collect();
+
+    let p_values: Vec<f32> = bits.iter().map(|b| b.p_value).collect();
+    for (bit, adjusted) in bits.iter_mut().zip(holm_adjust(&p_values)) {
+        bit.adjusted_p_value = adjusted;
+    }
+
+    let p_value = bits.iter().map(|b| b.adjusted_p_value).fold(1.0, f32::min);
+
+    Some(BitBiasTest { bits, p_value })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        bit_bias::{holm_adjust, test},
+        distribution::CriticalValue,
+        experiment::Experiment,
+        message::UniformMessages,
+        prng::Pcg32,
+        sample::Sample,
+    };
+    use checksum_tapestry::crc::CRC;
+
+    #[test]
+    fn holm_adjust_works() {
+        let check = |p_values: &[f32], expected: &[f32]| {
+            let adjusted = holm_adjust(p_values);
+            assert_eq!(adjusted.len(), expected.len());
+            for (a, e) in adjusted.iter().zip(expected.iter()) {
+                assert!(f32::abs(a - e) < 1.0e-6);
+            }
+        };
+
+        check(&[0.4, 0.9], &[0.8, 0.9]);
+        check(&[0.5, 0.9], &[1.0, 1.0]);
+        check(&[0.001, 0.5, 0.01, 0.2], &[0.004, 0.5, 0.03, 0.4]);
+        check(&[], &[]);
+    }
+
+    /// Test the z-scores and p-values on a small hand-computed example
+    #[test]
+    fn test_works_small() {
+        // Bit 0 is set in all 16 samples, bit 1 in half of them
+        let samples = (0..16u8)
+            .map(|i| Sample {
+                sample: 1 | ((i % 2) << 1),
+            })
+            .collect();
+        let experiment: Experiment<u8> = Experiment::from_samples(samples);
+
+        let result = test(&experiment).unwrap();
+        assert_eq!(result.bits.len(), 8);
+        assert_eq!(result.bits[0].proportion, 1.0);
+        assert_eq!(result.bits[0].z_score, 4.0);
+        // 2 * (1 - Phi(4))
+        assert!(f32::abs(result.bits[0].p_value - 6.334e-5) < 1.0e-7);
+        assert_eq!(result.bits[1].z_score, 0.0);
+        assert_eq!(result.bits[1].p_value, 1.0);
+        assert_eq!(result.bits[2].z_score, -4.0);
+        assert_eq!(result.biased_bits(CriticalValue::OnePercent).len(), 7);
+    }
+
+    /// A CRC of uniform messages has no biased bits
+    #[test]
+    fn test_works_crc32() {
+        let mut source = UniformMessages::new(Pcg32::new(2), 16);
+        let mut crc32 = CRC::<u32>::default();
+        let experiment = Experiment::<u32>::run_source(&mut source, &mut crc32, 2000);
+
+        let result = test(&experiment).unwrap();
+        assert!(!result.reject(CriticalValue::OnePercent));
+        assert!(result.biased_bits(CriticalValue::OnePercent).is_empty());
+    }
+
+    /// Empty experiments can't be tested
+    #[test]
+    fn test_empty_fails() {
+        let experiment: Experiment<u32> = Experiment::from_samples(vec![]);
+
+        assert!(test(&experiment).is_none());
+    }
+}
@@ -0,0 +1,368 @@
+//! Bit independence criterion test
+//! Measure whether flipping an input bit changes pairs of output bits
+//! independently of each other.
+//!
+//! A function meets the bit independence criterion (BIC) when, for
+//! every input bit, the changes to any two output bits are
+//! uncorrelated.  This complements the strict avalanche criterion in
+//! [`avalanche`](crate::analysis::avalanche): each output bit can flip
+//! half the time while always flipping together with another bit.
+//!
+//! Webster, A. F., & Tavares, S. E. (1986). On the Design of S-Boxes.
+//! Advances in Cryptology — CRYPTO '85.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+};
+
+use crate::analysis::{experiment::OutputWidth, message::MessageSource};
+use checksum_tapestry::Checksum;
+
+/// The number of pairs kept in [`BitIndependenceTest::worst_pairs`]
+pub const NUM_WORST_PAIRS: usize = 10;
+
+/// A pair of output bits whose changes are correlated when an input
+/// bit is flipped
+#[derive(Debug)]
+pub struct CorrelatedPair {
+    /// The flipped input bit, bit i % 8 of byte i / 8
+    pub input_bit: usize,
+    /// The two output bits, counting from the least significant bit
+    pub output_bits: (usize, usize),
+    /// The correlation between the changes to the two output bits
+    pub correlation: f32,
+}
+
+/// The result of a bit independence criterion test
+#[derive(Debug)]
+pub struct BitIndependenceTest {
+    /// The largest absolute correlation between changes to output
+    /// bits j and k over every input bit, indexed as correlations\[j\]\[k\]
+    ///
+    /// The diagonal is one.
+    pub correlations: Vec<Vec<f32>>,
+    /// The most correlated pairs, largest absolute correlation first
+    pub worst_pairs: Vec<CorrelatedPair>,
+    /// The largest absolute correlation of any pair, the BIC
+    /// parameter.  This is zero for an ideal function.
+    pub max_correlation: f32,
+}
+
+/// The correlation between two indicator variables, from the number
+/// of trials n, the number of times each was one, and the number of
+/// times both were one
+///
+/// An output bit that always or never changes is fixed by the input
+/// bit alone, so it's treated as fully dependent with a correlation
+/// of one.
+fn indicator_correlation(n: u64, count_j: u64, count_k: u64, count_jk: u64) -> f64 {
+    let n = n as f64;
+    let (j, k, jk) = (count_j as f64, count_k as f64, count_jk as f64);
+
+    let variance = j * (n - j) * k * (n - k);
+    if variance == 0.0 {
+        return 1.0;
+    }
+
+    (n * jk - j * k) / f64::sqrt(variance)
+}
+
+/// A correlated pair ordered by the size of its correlation, with
+/// earlier pairs ahead on ties
+struct RankedPair(CorrelatedPair);
+
+impl RankedPair {
+    fn key(&self) -> (usize, (usize, usize)) {
+        (self.0.input_bit, self.0.output_bits)
+    }
+}
+
+impl PartialEq for RankedPair {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RankedPair {}
+
+impl PartialOrd for RankedPair {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RankedPair {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .correlation
+            .abs()
+            .total_cmp(&other.0.correlation.abs())
+            .then_with(|| other.key().cmp(&self.key()))
+    }
+}
+
+/// Perform a bit independence criterion test on a checksum
+///
+/// num_messages messages are taken from the source.  For each one,
+/// every input bit is flipped in turn and the changed output bits are
+/// recorded.
+///
+/// The messages are kept while the test runs so each input bit can be
+/// counted in its own pass, and only the counts for one input bit are
+/// held at a time.  Memory grows with the total size of the messages
+/// plus the square of the output width, not with the number of input
+/// bits.
+///
+/// Returns None if there are fewer than two messages or every message
+/// is empty.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{
+///     bit_independence::test, message::UniformMessages, prng::Pcg32,
+/// };
+/// use checksum_tapestry::adler32::Adler32;
+///
+/// let mut source = UniformMessages::new(Pcg32::new(1), 8);
+/// let mut adler32 = Adler32::default();
+/// let result = test(&mut source, &mut adler32, 200).unwrap();
+///
+/// assert_eq!(result.correlations.len(), 32);
+/// assert!(result.max_correlation > 0.5);
+/// ```
+pub fn test<T: OutputWidth>(
+    source: &mut dyn MessageSource,
+    checksum: &mut dyn Checksum<T>,
+    num_messages: u32,
+) -> Option<BitIndependenceTest> {
+    let output_bits = T::BITS as usize;
+
+    // Each message with its checksum
+    let mut messages: Vec<(Vec<u8>, u64)> = (0..num_messages)
+        .map(|_| {
+            let mut message = vec![0; source.next_length()];
+            source.fill(&mut message);
+            let original = checksum.compute(&message).to_u64();
+            checksum.reset();
+
+            (message, original)
+        })
+        .collect();
+    let input_bits = messages.iter().map(|(m, _)| m.len() * 8).max().unwrap_or(0);
+
+    let mut correlations: Vec<Vec<f32>> = vec![vec![0.0; output_bits]; output_bits];
+    let mut worst: BinaryHeap<Reverse<RankedPair>> = BinaryHeap::new();
+    // The number of times each pair of output bits changed together
+    // for the current input bit.  The diagonal holds the number of
+    // times each bit changed.
+    let mut together: Vec<u64> = vec![0; output_bits * output_bits];
+    let mut tested = false;
+
+    for i in 0..input_bits {
+        let mask = 1u8 << (i % 8);
+        let mut trials: u64 = 0;
+        together.fill(0);
+
+        for (message, original) in messages.iter_mut().filter(|(m, _)| m.len() * 8 > i) {
+            message[i / 8] ^= mask;
+            let flipped = checksum.compute(message).to_u64();
+            checksum.reset();
+            message[i / 8] ^= mask;
+
+            let difference = *original ^ flipped;
+            for j in (0..output_bits).filter(|j| (difference >> j) & 1 == 1) {
+                for k in (j..output_bits).filter(|k| (difference >> k) & 1 == 1) {
+                    together[j * output_bits + k] += 1;
+                }
+            }
+            trials += 1;
+        }
+
+        if trials < 2 {
+            continue;
+        }
+        tested = true;
+
+        for j in 0..output_bits {
+            for k in (j + 1)..output_bits {
+                let correlation = indicator_correlation(
+                    trials,
+                    together[j * output_bits + j],
+                    together[k * output_bits + k],
+                    together[j * output_bits + k],
+                ) as f32;
+
+                let max = f32::max(correlations[j][k], correlation.abs());
+                correlations[j][k] = max;
+                correlations[k][j] = max;
+
+                // Keep the most correlated pairs, replacing the least
+                // correlated one kept once there are enough
+                let pair = RankedPair(CorrelatedPair {
+                    input_bit: i,
+                    output_bits: (j, k),
+                    correlation,
+                });
+                if worst.len() < NUM_WORST_PAIRS {
+                    worst.push(Reverse(pair));
+                } else if let Some(mut least) = worst.peek_mut() {
+                    if pair > least.0 {
+                        *least = Reverse(pair);
+                    }
+                }
+            }
+        }
+    }
+
+    if !tested {
+        return None;
+    }
+
+    for (j, row) in correlations.iter_mut().enumerate() {
+        row[j] = 1.0;
+    }
+
+    let worst_pairs: Vec<CorrelatedPair> = worst
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(ranked)| ranked.0)
+        .collect();
+    let max_correlation = worst_pairs.first().map_or(0.0, |p| p.correlation.abs());
+
+    Some(BitIndependenceTest {
+        correlations,
+        worst_pairs,
+        max_correlation,
+    })
+}
+
+impl BitIndependenceTest {
+    /// Draw the correlation matrix as a heatmap on a terminal
+    /// This function has side effects
+    ///
+    /// Each cell shows the largest absolute correlation for that pair
+    /// of output bits, from blank for none to @ for complete
+    /// dependence.  The most significant bit is in the top left.
+    ///
+    /// # Examples
+    /// ```
+    /// use tapestry_analysis::analysis::{
+    ///     bit_independence::test, message::UniformMessages, prng::Pcg32,
+    /// };
+    /// use checksum_tapestry::fletcher16::Fletcher16;
+    ///
+    /// let mut source = UniformMessages::new(Pcg32::new(1), 8);
+    /// let mut fletcher16 = Fletcher16::default();
+    /// let result = test(&mut source, &mut fletcher16, 100).unwrap();
+    /// result.draw_terminal();
+    /// ```
+    pub fn draw_terminal(&self) {
+        let shades = [' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+        let output_bits = self.correlations.len();
+
+        // Column labels, the tens digit then the ones digit
+        for digit in [10, 1] {
+            print!("    ");
+            for k in (0..output_bits).rev() {
+                print!("{}", (k / digit) % 10);
+            }
+            println!();
+        }
+
+        for j in (0..output_bits).rev() {
+            print!("{j:>3} ");
+            for k in (0..output_bits).rev() {
+                if j == k {
+                    print!("\\");
+                } else {
+                    let level = (self.correlations[j][k] * (shades.len() - 1) as f32).round();
+                    print!("{}", shades[level as usize]);
+                }
+            }
+            println!();
+        }
+
+        println!("Most correlated pairs:");
+        for pair in &self.worst_pairs {
+            println!(
+                "input bit {:>4}, output bits {:>2} and {:>2}: {:.3}",
+                pair.input_bit, pair.output_bits.0, pair.output_bits.1, pair.correlation
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        bit_independence::{indicator_correlation, test, NUM_WORST_PAIRS},
+        message::UniformMessages,
+        prng::Pcg32,
+        test_support::Mixed,
+    };
+    use checksum_tapestry::crc::CRC;
+
+    #[test]
+    fn indicator_correlation_works() {
+        // Always together
+        assert_eq!(indicator_correlation(10, 5, 5, 5), 1.0);
+        // Never together
+        assert_eq!(indicator_correlation(10, 5, 5, 0), -1.0);
+        // Independent
+        assert_eq!(indicator_correlation(4, 2, 2, 1), 0.0);
+        // Constant
+        assert_eq!(indicator_correlation(10, 10, 5, 5), 1.0);
+    }
+
+    /// A CRC's output changes are fixed by the flipped input bit
+    #[test]
+    fn test_works_crc32() {
+        let mut source = UniformMessages::new(Pcg32::new(1), 4);
+        let mut crc32 = CRC::<u32>::default();
+        let result = test(&mut source, &mut crc32, 50).unwrap();
+
+        assert_eq!(result.max_correlation, 1.0);
+        assert_eq!(result.worst_pairs.len(), NUM_WORST_PAIRS);
+        // Every pair ties, so the first pairs are kept in order
+        for (k, pair) in result.worst_pairs.iter().enumerate() {
+            assert_eq!((pair.input_bit, pair.output_bits), (0, (0, k + 1)));
+        }
+        assert!(result.correlations.iter().flatten().all(|c| *c == 1.0));
+    }
+
+    /// A well mixed function has no strongly correlated pairs
+    #[test]
+    fn test_works_mixed() {
+        let mut source = UniformMessages::new(Pcg32::new(2), 2);
+        let mut mixed = Mixed::default();
+        let result = test(&mut source, &mut mixed, 2000).unwrap();
+
+        assert_eq!(result.correlations.len(), 64);
+        assert!(result.max_correlation < 0.15);
+        for pair in &result.worst_pairs {
+            assert!(pair.output_bits.0 < pair.output_bits.1);
+            assert!(pair.correlation.abs() <= result.max_correlation);
+        }
+        for (a, b) in result
+            .worst_pairs
+            .iter()
+            .zip(result.worst_pairs.iter().skip(1))
+        {
+            assert!(a.correlation.abs() >= b.correlation.abs());
+        }
+        result.draw_terminal();
+    }
+
+    /// A single message can't give a correlation
+    #[test]
+    fn test_too_few_messages_fails() {
+        let mut source = UniformMessages::new(Pcg32::new(3), 4);
+        let mut crc32 = CRC::<u32>::default();
+
+        assert!(test(&mut source, &mut crc32, 1).is_none());
+    }
+}
@@ -0,0 +1,356 @@
+//! Burst-error detection guarantees for CRCs
+//! Check every burst error pattern up to a given length against a
+//! CRC, to confirm it detects them all.
+//!
+//! A burst of length L is an error pattern whose first and last
+//! flipped bits are L - 1 bits apart, in transmission order.  A CRC
+//! with a generator polynomial of degree r and a non-zero constant
+//! term detects every burst of length r or less.  A configuration
+//! with the wrong polynomial, or the wrong bit order for the link,
+//! loses that guarantee.
+//!
+//! CRCs are linear: the change to the CRC from an error pattern is
+//! the XOR of the changes from each flipped bit, whatever the
+//! message.  So the change from every single bit flip is computed
+//! once, and each burst pattern is checked with XORs instead of
+//! computing a new CRC.  There are 2^(L - 2) patterns of length L at
+//! each offset, so checking up to 32-bit bursts takes a long time.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::{
+    crc_polynomial::CrcWidth, error_detection::flip_bit, experiment::OutputWidth,
+};
+use checksum_tapestry::{
+    crc::{BitOrder, CRCConfiguration},
+    Checksum,
+};
+
+/// The most undetected bursts kept in [`BurstTest::undetected`]
+pub const MAX_REPORTED_BURSTS: usize = 100;
+
+/// A burst error pattern the checksum didn't detect
+#[derive(Debug, PartialEq)]
+pub struct UndetectedBurst {
+    /// The position of the first flipped bit, in transmission order
+    pub offset: usize,
+    /// The length of the burst in bits
+    pub length: usize,
+    /// The flipped bits, bit j is set if bit offset + j was flipped
+    pub pattern: u64,
+}
+
+/// The result of a burst error check
+#[derive(Debug)]
+pub struct BurstTest {
+    /// The length of the messages checked in bytes
+    pub message_length: usize,
+    /// The longest burst checked in bits
+    pub max_burst_length: usize,
+    /// The number of burst patterns checked
+    pub patterns_checked: u64,
+    /// The number of burst patterns that weren't detected
+    pub num_undetected: u64,
+    /// The first undetected bursts, shortest first, up to
+    /// [`MAX_REPORTED_BURSTS`] of them
+    pub undetected: Vec<UndetectedBurst>,
+    /// The longest burst length where every burst up to that length
+    /// was detected
+    pub detects_all_up_to: usize,
+}
+
+/// The change in the checksum from flipping each bit of a message of
+/// the given length, in transmission order
+///
+/// Returns None if the changes aren't the same for two different
+/// messages, which means the checksum isn't linear.
+fn bit_differences<T: OutputWidth>(
+    checksum: &mut dyn Checksum<T>,
+    message_length: usize,
+    bit_order: BitOrder,
+) -> Option<Vec<u64>> {
+    let zeros: Vec<u8> = vec![0; message_length];
+    let mixed: Vec<u8> = (0..message_length)
+        .map(|i| (i as u8).wrapping_mul(0x9E) ^ 0x5A)
+        .collect();
+
+    let mut differences: Vec<u64> = Vec::with_capacity(message_length * 8);
+    for base in [&zeros, &mixed] {
+        let original = checksum.compute(base).to_u64();
+        checksum.reset();
+
+        let mut message = base.clone();
+        for i in 0..message_length * 8 {
+            flip_bit(&mut message, i, bit_order);
+            let difference = checksum.compute(&message).to_u64() ^ original;
+            checksum.reset();
+            flip_bit(&mut message, i, bit_order);
+
+            if base == &zeros {
+                differences.push(difference);
+            } else if differences[i] != difference {
+                return None;
+            }
+        }
+    }
+
+    Some(differences)
+}
+
+/// Check every burst error pattern up to max_burst_length bits long,
+/// at every offset in a message of message_length bytes
+///
+/// Bits are numbered in transmission order for the given bit order,
+/// which should be the bit order of the CRC.
+///
+/// Returns None if the checksum isn't linear, like Adler-32, because
+/// the check wouldn't be exhaustive.
+///
+/// # Panics
+///
+/// Panics if max_burst_length is greater than 64.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::burst::test;
+/// use checksum_tapestry::crc::{BitOrder, CRC};
+///
+/// // The default CRC-32 configuration is MSB first
+/// let mut crc32 = CRC::<u32>::default();
+/// let result = test(&mut crc32, BitOrder::MSBFirst, 8, 12).unwrap();
+/// assert_eq!(result.num_undetected, 0);
+/// assert_eq!(result.detects_all_up_to, 12);
+/// ```
+pub fn test<T: OutputWidth>(
+    checksum: &mut dyn Checksum<T>,
+    bit_order: BitOrder,
+    message_length: usize,
+    max_burst_length: usize,
+) -> Option<BurstTest> {
+    assert!(max_burst_length <= 64, "max_burst_length must be <= 64");
+
+    let differences = bit_differences(checksum, message_length, bit_order)?;
+    let message_bits = message_length * 8;
+    let max_burst_length = max_burst_length.min(message_bits);
+
+    let mut patterns_checked: u64 = 0;
+    let mut num_undetected: u64 = 0;
+    let mut undetected: Vec<UndetectedBurst> = Vec::new();
+    let mut detects_all_up_to = max_burst_length;
+
+    for length in 1..=max_burst_length {
+        let undetected_before = num_undetected;
+
+        for offset in 0..=(message_bits - length) {
+            let last = offset + length - 1;
+            let mut difference = differences[offset];
+            let mut pattern: u64 = 1;
+            if length > 1 {
+                difference ^= differences[last];
+                pattern |= 1 << (length - 1);
+            }
+
+            // Step through every set of middle bits in Gray code
+            // order, so each step flips a single bit
+            let middle_bits = length.saturating_sub(2);
+            for step in 0..(1u64 << middle_bits) {
+                if step > 0 {
+                    let bit = step.trailing_zeros() as usize + 1;
+                    difference ^= differences[offset + bit];
+                    pattern ^= 1 << bit;
+                }
+
+                patterns_checked += 1;
+                if difference == 0 {
+                    num_undetected += 1;
+                    if undetected.len() < MAX_REPORTED_BURSTS {
+                        undetected.push(UndetectedBurst {
+                            offset,
+                            length,
+                            pattern,
+                        });
+                    }
+                }
+            }
+        }
+
+        if num_undetected > undetected_before && detects_all_up_to == max_burst_length {
+            detects_all_up_to = length - 1;
+        }
+    }
+
+    Some(BurstTest {
+        message_length,
+        max_burst_length,
+        patterns_checked,
+        num_undetected,
+        undetected,
+        detects_all_up_to,
+    })
+}
+
+/// Check a CRC configuration against every burst up to
+/// max_burst_length bits long, in the configuration's bit order
+///
+/// A good configuration detects every burst up to its width, so
+/// checking up to the width should leave nothing undetected.  Pass
+/// the width plus one to see the first bursts it misses, but there
+/// are 2^(L - 2) patterns of length L at each offset, so for a 32-bit
+/// CRC that takes minutes.
+///
+/// Returns None if the configuration isn't linear, which can't happen
+/// for a valid CRC.
+///
+/// # Panics
+///
+/// Panics if max_burst_length is greater than 64.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::burst::test_configuration;
+/// use checksum_tapestry::crc::{BitOrder, BitWidth, CRCConfiguration};
+///
+/// let configuration = CRCConfiguration::<u16>::new(
+///     "CRC-16/ARC",
+///     BitWidth::Sixteen,
+///     BitOrder::LSBFirst,
+///     0x8005,
+///     true,
+///     None,
+///     None,
+/// );
+/// let result = test_configuration(configuration, 8, 17).unwrap();
+/// assert_eq!(result.detects_all_up_to, 16);
+/// ```
+pub fn test_configuration<T: CrcWidth>(
+    configuration: CRCConfiguration<T>,
+    message_length: usize,
+    max_burst_length: usize,
+) -> Option<BurstTest> {
+    let bit_order = configuration.bit_order;
+    let mut crc = T::crc(configuration);
+
+    test(crc.as_mut(), bit_order, message_length, max_burst_length)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::burst::{test, test_configuration, UndetectedBurst};
+    use checksum_tapestry::{
+        adler32::Adler32,
+        crc::{BitOrder, BitWidth, CRCConfiguration, CRC},
+    };
+
+    /// CRC-16/ARC detects every burst up to 16 bits, and misses the
+    /// 17-bit bursts equal to its generator polynomial
+    #[test]
+    fn test_works_crc16() {
+        let configuration = CRCConfiguration::<u16>::new(
+            "CRC-16/ARC",
+            BitWidth::Sixteen,
+            BitOrder::LSBFirst,
+            0x8005,
+            true,
+            None,
+            None,
+        );
+        let result = test_configuration(configuration, 6, 17).unwrap();
+
+        assert_eq!(result.max_burst_length, 17);
+        assert_eq!(result.detects_all_up_to, 16);
+        // One undetected pattern at each offset, out of 2^15
+        assert_eq!(result.num_undetected, 48 - 17 + 1);
+        // x^16 + x^15 + x^2 + 1, highest power first in transmission
+        // order, so reversed in the pattern
+        assert_eq!(
+            result.undetected[0],
+            UndetectedBurst {
+                offset: 0,
+                length: 17,
+                pattern: 0x1_4003,
+            }
+        );
+    }
+
+    /// A polynomial without a constant term is divisible by x, and
+    /// misses shorter bursts
+    #[test]
+    fn test_works_even_polynomial() {
+        let configuration = CRCConfiguration::<u16>::new(
+            "CRC-16/EVEN",
+            BitWidth::Sixteen,
+            BitOrder::MSBFirst,
+            0x8004,
+            false,
+            None,
+            None,
+        );
+        let result = test_configuration(configuration, 4, 17).unwrap();
+
+        // x^16 + x^15 + x^2 = x^2 (x^14 + x^13 + 1)
+        assert_eq!(result.detects_all_up_to, 14);
+        assert_eq!(result.undetected[0].length, 15);
+    }
+
+    /// CRC-32 detects every short burst, checking each pattern once
+    #[test]
+    fn test_works_crc32() {
+        let mut crc32 = CRC::<u32>::default();
+
+        let result = test(&mut crc32, BitOrder::MSBFirst, 4, 16).unwrap();
+        assert_eq!(result.num_undetected, 0);
+        assert!(result.undetected.is_empty());
+        assert_eq!(result.detects_all_up_to, 16);
+        // 32 single bit bursts, then 2^(L - 2) patterns at each offset
+        let expected: u64 = 32 + (2..=16).map(|l| (33 - l) << (l - 2)).sum::<u64>();
+        assert_eq!(result.patterns_checked, expected);
+    }
+
+    /// A reflected CRC-32 configuration is checked in its own bit
+    /// order
+    #[test]
+    fn test_configuration_works_crc32() {
+        let configuration = CRCConfiguration::<u32>::new(
+            "CRC-32/ISO-HDLC",
+            BitWidth::ThirtyTwo,
+            BitOrder::LSBFirst,
+            0x04C11DB7,
+            true,
+            Some(0xFFFFFFFF),
+            Some(0xFFFFFFFF),
+        );
+        let result = test_configuration(configuration, 4, 16).unwrap();
+
+        assert_eq!(result.num_undetected, 0);
+        assert_eq!(result.detects_all_up_to, 16);
+    }
+
+    /// Checking a reflected CRC in the wrong bit order breaks the
+    /// burst guarantee
+    #[test]
+    fn test_wrong_bit_order() {
+        let configuration = CRCConfiguration::<u16>::new(
+            "CRC-16/ARC",
+            BitWidth::Sixteen,
+            BitOrder::LSBFirst,
+            0x8005,
+            true,
+            None,
+            None,
+        );
+        let mut crc16 = CRC::<u16>::new(configuration, true);
+
+        let result = test(&mut crc16, BitOrder::MSBFirst, 6, 16).unwrap();
+        assert!(result.detects_all_up_to < 16);
+    }
+
+    /// Adler-32 isn't linear, so it can't be checked this way
+    #[test]
+    fn test_nonlinear_fails() {
+        let mut adler32 = Adler32::default();
+
+        assert!(test(&mut adler32, BitOrder::LSBFirst, 4, 8).is_none());
+    }
+}
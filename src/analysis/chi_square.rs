@@ -0,0 +1,265 @@
+//! Chi-square goodness of fit test
+//! Perform a Pearson chi-square goodness of fit test on binned data
+//! This can be used to find if a set of values comes from a given
+//! distribution.
+//! Unlike the Kolmogorov–Smirnov test, this works on histogram bins
+//! instead of the raw values.  It's more sensitive to output that is
+//! clumped into a few regions, such as Adler-32 with short messages.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::{
+    distribution::CriticalValue,
+    experiment::{Experiment, OutputWidth},
+    histogram::{Histogram, SimpleHistogram},
+    special::{bisect, regularized_gamma_p, regularized_gamma_q},
+};
+
+/// The result of a chi-square goodness of fit test
+#[derive(Debug)]
+pub struct ChiSquareTest {
+    /// The chi-square test statistic
+    pub statistic: f32,
+    /// The degrees of freedom, the number of bins minus one
+    pub degrees_of_freedom: u32,
+    /// The probability of seeing a statistic at least this large if
+    /// the data follows the expected distribution
+    pub p_value: f32,
+}
+
+impl ChiSquareTest {
+    /// Returns true if the null hypothesis, that the data follows the
+    /// expected distribution, should be rejected at the given
+    /// significance level
+    pub fn reject(&self, cv: CriticalValue) -> bool {
+        self.p_value < cv.alpha()
+    }
+}
+
+/// Expected bin probabilities for a uniform distribution over the
+/// given number of bins
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::chi_square::uniform_probabilities;
+///
+/// assert_eq!(uniform_probabilities(4), [0.25, 0.25, 0.25, 0.25]);
+/// ```
+pub fn uniform_probabilities(num_bins: u8) -> Vec<f32> {
+    vec![1.0 / num_bins as f32; num_bins.into()]
+}
+
+/// Calculate the chi-square test statistic
+/// This sums (observed - expected)^2 / expected over every bin.
+///
+/// expected_probabilities is the probability of a sample falling into
+/// each bin.  Returns None if the number of bins doesn't match or
+/// any expected probability is zero or negative.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::chi_square::{statistic, uniform_probabilities};
+///
+/// let observed = [22, 21, 22, 27, 22, 36];
+/// let statistic = statistic(&observed, &uniform_probabilities(6)).unwrap();
+/// assert!(f32::abs(statistic - 6.72) < 0.0001);
+/// ```
+pub fn statistic(observed: &[u32], expected_probabilities: &[f32]) -> Option<f32> {
+    if observed.len() != expected_probabilities.len() {
+        return None;
+    }
+    if expected_probabilities.iter().any(|p| *p <= 0.0) {
+        return None;
+    }
+
+    let n: f64 = observed.iter().map(|o| *o as f64).sum();
+
+    let mut statistic: f64 = 0.0;
+    for (o, p) in observed.iter().zip(expected_probabilities) {
+        let expected = n * *p as f64;
+        let difference = *o as f64 - expected;
+        statistic += difference * difference / expected;
+    }
+
+    Some(statistic as f32)
+}
+
+/// Compute the p-value for a chi-square statistic
+/// This is the upper tail of the chi-square distribution with the
+/// given degrees of freedom.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::chi_square::p_value;
+///
+/// let p = p_value(6.72, 5);
+/// assert!(f32::abs(p - 0.2423) < 0.001);
+/// ```
+pub fn p_value(statistic: f32, degrees_of_freedom: u32) -> f32 {
+    regularized_gamma_q(degrees_of_freedom as f64 / 2.0, statistic as f64 / 2.0) as f32
+}
+
+/// Compute the chi-square critical value for the given degrees of
+/// freedom
+///
+/// Statistics above this value reject the null hypothesis at the
+/// significance level of cv.  Returns None for zero degrees of
+/// freedom.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{chi_square::critical_value, distribution::CriticalValue};
+///
+/// let critical_value = critical_value(CriticalValue::FivePercent, 9).unwrap();
+/// assert!(f32::abs(critical_value - 16.919) < 0.001);
+/// ```
+// The equivalent using the Python SciPy package is:
+// stats.chi2.ppf([1-0.10, 1-0.05, 1-0.01], df)
+pub fn critical_value(cv: CriticalValue, degrees_of_freedom: u32) -> Option<f32> {
+    if degrees_of_freedom == 0 {
+        return None;
+    }
+
+    let k = degrees_of_freedom as f64 / 2.0;
+    let target = 1.0 - cv.alpha() as f64;
+    // The chi-square distribution has mean df and variance 2 df, so
+    // the quantiles we're interested in are well below this bound
+    let high = degrees_of_freedom as f64 + 20.0 * f64::sqrt(degrees_of_freedom as f64) + 20.0;
+
+    Some(bisect(|x| regularized_gamma_p(k, x / 2.0), target, 0.0, high) as f32)
+}
+
+/// Perform a chi-square test on a histogram
+///
+/// expected_probabilities should have one entry for each bin.
+/// Returns None if the bin counts don't match, an expected
+/// probability isn't positive or there are fewer than two bins.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{
+///     chi_square::{test, uniform_probabilities},
+///     distribution::DiscreteUniformDistribution,
+///     experiment::Experiment,
+///     histogram::{Histogram, SimpleHistogram},
+/// };
+/// use checksum_tapestry::adler32::Adler32;
+///
+/// let mut dud = DiscreteUniformDistribution::new(0, u32::MAX);
+///
+/// // Short messages only use the bottom of the Adler-32 output space
+/// let mut adler32 = Adler32::default();
+/// let experiment = Experiment::<u32>::run(&mut dud, &mut adler32, 50, 1000);
+///
+/// let histogram = SimpleHistogram::new(&experiment, 10);
+/// let result = test(&histogram, &uniform_probabilities(10)).unwrap();
+/// assert_eq!(result.degrees_of_freedom, 9);
+/// assert!(result.p_value < 0.01);
+/// ```
+pub fn test(histogram: &SimpleHistogram, expected_probabilities: &[f32]) -> Option<ChiSquareTest> {
+    if histogram.bins.len() < 2 {
+        return None;
+    }
+
+    let statistic = statistic(&histogram.bins, expected_probabilities)?;
+    let degrees_of_freedom: u32 = (histogram.bins.len() - 1).try_into().unwrap();
+
+    Some(ChiSquareTest {
+        statistic,
+        degrees_of_freedom,
+        p_value: p_value(statistic, degrees_of_freedom),
+    })
+}
+
+/// Perform a chi-square test on an experiment
+///
+/// The experiment is binned into a SimpleHistogram with one bin for
+/// each expected probability, so there can be at most 255 bins.
+pub fn test_experiment<T: OutputWidth>(
+    experiment: &Experiment<T>,
+    expected_probabilities: &[f32],
+) -> Option<ChiSquareTest> {
+    let num_bins: u8 = expected_probabilities.len().try_into().ok()?;
+    let histogram = SimpleHistogram::new(experiment, num_bins);
+
+    test(&histogram, expected_probabilities)
+}
+
+/// Test examples comes from several sources, including:
+/// PennState STAT 415 Introduction to Mathematical Statistics
+/// https://online.stat.psu.edu/stat415/
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        chi_square::{critical_value, p_value, statistic, test, uniform_probabilities},
+        distribution::CriticalValue,
+        histogram::SimpleHistogram,
+    };
+
+    /// Fair die example, 150 rolls
+    #[test]
+    fn statistic_works_die() {
+        let observed = [22, 21, 22, 27, 22, 36];
+        let statistic = statistic(&observed, &uniform_probabilities(6)).unwrap();
+
+        assert!(f32::abs(statistic - 6.72) < 0.0001);
+    }
+
+    /// Test non-uniform expected probabilities
+    /// PennState STAT 415, Mendel's peas example
+    #[test]
+    fn statistic_works_non_uniform() {
+        let observed = [315, 108, 101, 32];
+        let expected = [9.0 / 16.0, 3.0 / 16.0, 3.0 / 16.0, 1.0 / 16.0];
+        let statistic = statistic(&observed, &expected).unwrap();
+
+        assert!(f32::abs(statistic - 0.470) < 0.001);
+    }
+
+    /// Mismatched or invalid expected probabilities return None
+    #[test]
+    fn statistic_invalid_expected_fails() {
+        assert!(statistic(&[1, 2, 3], &uniform_probabilities(2)).is_none());
+        assert!(statistic(&[1, 2], &[1.0, 0.0]).is_none());
+    }
+
+    /// Test critical values against a statistical table
+    #[test]
+    fn critical_value_works() {
+        let crit_val = critical_value(CriticalValue::TenPercent, 9).unwrap();
+        assert!(f32::abs(crit_val - 14.684) < 0.001);
+        let crit_val = critical_value(CriticalValue::FivePercent, 9).unwrap();
+        assert!(f32::abs(crit_val - 16.919) < 0.001);
+        let crit_val = critical_value(CriticalValue::OnePercent, 9).unwrap();
+        assert!(f32::abs(crit_val - 21.666) < 0.001);
+
+        let crit_val = critical_value(CriticalValue::FivePercent, 1).unwrap();
+        assert!(f32::abs(crit_val - 3.841) < 0.001);
+
+        assert!(critical_value(CriticalValue::FivePercent, 0).is_none());
+    }
+
+    /// The p-value at the critical value should be alpha
+    #[test]
+    fn p_value_works() {
+        assert!(f32::abs(p_value(16.919, 9) - 0.05) < 0.0001);
+        assert!(f32::abs(p_value(3.841, 1) - 0.05) < 0.0001);
+        assert_eq!(p_value(0.0, 9), 1.0);
+    }
+
+    /// Test the full pipeline on a histogram
+    #[test]
+    fn test_works() {
+        let histogram = SimpleHistogram::from_bins(vec![22, 21, 22, 27, 22, 36], 8);
+        let result = test(&histogram, &uniform_probabilities(6)).unwrap();
+
+        assert_eq!(result.degrees_of_freedom, 5);
+        assert!(f32::abs(result.statistic - 6.72) < 0.0001);
+        assert!(!result.reject(CriticalValue::FivePercent));
+    }
+}
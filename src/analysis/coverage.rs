@@ -0,0 +1,410 @@
+//! Output-space coverage and collision statistics
+//! Count how much of a checksum's output space an experiment reaches
+//! and how often outputs repeat.
+//! A good checksum spreads messages over every output, so the number
+//! of distinct outputs and collisions should match an ideal uniform
+//! function.  Too many collisions means part of the output space is
+//! wasted, the way Adler-32 wastes most of its space on short
+//! messages.
+//!
+//! The expected values come from the birthday problem.  With n
+//! samples thrown uniformly into N = 2^bits outputs:
+//! E[distinct] = N (1 - (1 - 1/N)^n)
+//! E[collisions] = n - E[distinct]
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::{
+    experiment::{Experiment, OutputWidth},
+    message::{ExhaustiveMessages, MessageSource},
+    special::{normal_cdf, regularized_gamma_p},
+};
+use checksum_tapestry::Checksum;
+
+/// The result of a coverage analysis
+#[derive(Debug)]
+pub struct Coverage {
+    /// The number of samples in the experiment
+    pub num_samples: u64,
+    /// The number of different outputs seen
+    pub distinct: u64,
+    /// The number of samples that repeat an output seen earlier,
+    /// num_samples - distinct
+    pub collisions: u64,
+    /// The fraction of the whole output space that was seen
+    pub coverage: f64,
+    /// The expected number of distinct outputs for a uniform function
+    pub expected_distinct: f64,
+    /// The expected number of collisions for a uniform function
+    pub expected_collisions: f64,
+    /// The probability of seeing at least this many collisions from a
+    /// uniform function
+    pub p_value: f32,
+}
+
+/// A bitmap of every output of a checksum, for counting distinct
+/// outputs as they're produced
+///
+/// The bitmap takes 2^bits bits, 512 MiB for a 32-bit output.
+pub struct OutputBitmap {
+    /// One bit for each output, set once the output has been seen
+    words: Vec<u64>,
+    /// The number of different outputs seen
+    pub distinct: u64,
+    /// The number of outputs inserted
+    pub num_samples: u64,
+}
+
+impl OutputBitmap {
+    /// The widest output a bitmap can hold
+    pub const MAX_BITS: u32 = 32;
+
+    /// Create an empty bitmap for outputs of the given width
+    ///
+    /// Returns None if bits is greater than [`OutputBitmap::MAX_BITS`].
+    pub fn new(bits: u32) -> Option<OutputBitmap> {
+        if bits > Self::MAX_BITS {
+            return None;
+        }
+
+        Some(OutputBitmap {
+            words: vec![0; ((1u64 << bits) / 64).max(1) as usize],
+            distinct: 0,
+            num_samples: 0,
+        })
+    }
+
+    /// Add an output, returning true if it hasn't been seen before
+    pub fn insert(&mut self, value: u64) -> bool {
+        let word = &mut self.words[(value / 64) as usize];
+        let bit = 1u64 << (value % 64);
+        self.num_samples += 1;
+        if *word & bit == 0 {
+            *word |= bit;
+            self.distinct += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Count the number of distinct outputs in an experiment
+///
+/// Outputs up to 32 bits wide are counted with a bitmap of the whole
+/// output space when that is no larger than sorting a copy of the
+/// samples, 2^32 outputs take 512 MiB.  Otherwise the samples are
+/// sorted.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{coverage::distinct_outputs, experiment::Experiment, sample::Sample};
+///
+/// let data: [u8; 5] = [1, 2, 2, 3, 1];
+/// let samples = data.iter().map(|d| Sample { sample: *d }).collect();
+/// let experiment: Experiment<u8> = Experiment::from_samples(samples);
+/// assert_eq!(distinct_outputs(&experiment), 3);
+/// ```
+pub fn distinct_outputs<T: OutputWidth>(experiment: &Experiment<T>) -> u64 {
+    let n = experiment.samples.len() as u64;
+
+    if T::BITS <= 32 && (1u64 << T::BITS) <= 64 * n {
+        let mut bitmap = OutputBitmap::new(T::BITS).unwrap();
+        for sample in &experiment.samples {
+            bitmap.insert(sample.sample.to_u64());
+        }
+
+        bitmap.distinct
+    } else {
+        let mut values: Vec<u64> = experiment
+            .samples
+            .iter()
+            .map(|s| s.sample.to_u64())
+            .collect();
+        values.sort_unstable();
+        values.dedup();
+
+        values.len() as u64
+    }
+}
+
+/// The probability an output is never hit by n samples,
+/// (1 - 1/N)^n with N = 2^bits
+fn miss_probability(n: u64, bits: u32) -> f64 {
+    let space = f64::powi(2.0, bits as i32);
+
+    f64::exp(n as f64 * f64::ln_1p(-1.0 / space))
+}
+
+/// The expected number of distinct outputs when n samples are drawn
+/// uniformly from 2^bits outputs
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::coverage::expected_distinct;
+///
+/// // Two samples from a one bit space are different half the time
+/// assert!(f64::abs(expected_distinct(2, 1) - 1.5) < 1.0e-12);
+/// ```
+pub fn expected_distinct(n: u64, bits: u32) -> f64 {
+    let space = f64::powi(2.0, bits as i32);
+
+    -space * f64::exp_m1(n as f64 * f64::ln_1p(-1.0 / space))
+}
+
+/// The expected number of collisions when n samples are drawn
+/// uniformly from 2^bits outputs
+///
+/// For n much smaller than 2^bits this is about n^2 / 2^(bits + 1).
+pub fn expected_collisions(n: u64, bits: u32) -> f64 {
+    (n as f64 - expected_distinct(n, bits)).max(0.0)
+}
+
+/// The variance of the number of distinct outputs, which is also the
+/// variance of the number of collisions
+///
+/// Var = N q1 (1 - q1) + N (N - 1) (q2 - q1^2), where q1 = (1 - 1/N)^n
+/// and q2 = (1 - 2/N)^n.  q2 - q1^2 is computed as
+/// q1^2 ((1 - 1 / (N - 1)^2)^n - 1) so it doesn't cancel to zero for
+/// large N.
+fn collisions_variance(n: u64, bits: u32) -> f64 {
+    let space = f64::powi(2.0, bits as i32);
+    if n == 0 {
+        return 0.0;
+    }
+
+    let q1 = miss_probability(n, bits);
+    let ratio = f64::ln_1p(-1.0 / ((space - 1.0) * (space - 1.0)));
+    let pair = q1 * q1 * f64::exp_m1(n as f64 * ratio);
+
+    (space * q1 * (1.0 - q1) + space * (space - 1.0) * pair).max(0.0)
+}
+
+/// Compute the probability of seeing at least the given number of
+/// collisions when n samples are drawn uniformly from 2^bits outputs
+///
+/// When few collisions are expected, the count is close to a Poisson
+/// distribution with the expected number of collisions as its mean.
+/// When many are expected, a normal approximation with the exact
+/// variance is used instead, because the Poisson variance is too
+/// large once a good part of the output space is filled.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::coverage::p_value;
+///
+/// // About 7.6 collisions are expected from 1000 16-bit outputs
+/// let p = p_value(10, 1000, 16);
+/// assert!(f32::abs(p - 0.2332) < 0.0001);
+/// ```
+pub fn p_value(collisions: u64, n: u64, bits: u32) -> f32 {
+    if collisions == 0 {
+        return 1.0;
+    }
+
+    let expected = expected_collisions(n, bits);
+
+    let p = if expected <= 50.0 {
+        // P(X >= c) for X ~ Poisson(expected)
+        regularized_gamma_p(collisions as f64, expected)
+    } else {
+        // With a continuity correction
+        let sd = f64::sqrt(collisions_variance(n, bits));
+        1.0 - normal_cdf((collisions as f64 - 0.5 - expected) / sd)
+    };
+
+    p.clamp(0.0, 1.0) as f32
+}
+
+/// Measure the output-space coverage of an experiment
+///
+/// The size of the output space comes from the experiment's output
+/// type.  Returns None if the experiment is empty.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{coverage::test, experiment::Experiment};
+/// use checksum_tapestry::adler32::Adler32;
+///
+/// // Every two byte message has a different Adler-32, but they only
+/// // cover a tiny part of the 32-bit space
+/// let mut adler32 = Adler32::default();
+/// let experiment = Experiment::<u32>::run_exhaustive(&mut adler32, 2);
+/// let coverage = test(&experiment).unwrap();
+/// assert_eq!(coverage.distinct, 65536);
+/// assert_eq!(coverage.collisions, 0);
+/// assert!(coverage.coverage < 0.00002);
+/// ```
+pub fn test<T: OutputWidth>(experiment: &Experiment<T>) -> Option<Coverage> {
+    if experiment.samples.is_empty() {
+        return None;
+    }
+
+    let num_samples = experiment.samples.len() as u64;
+
+    Some(coverage(num_samples, distinct_outputs(experiment), T::BITS))
+}
+
+/// Measure the output-space coverage of a checksum over every message
+/// from an exhaustive source
+///
+/// The outputs are counted in an [`OutputBitmap`] as they're produced,
+/// so this doesn't keep a sample for each message and works for every
+/// message of up to four bytes.  Checksumming all 2^32 four byte
+/// messages takes a while.
+///
+/// Returns None if the checksum is wider than
+/// [`OutputBitmap::MAX_BITS`] or the source has no messages.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{coverage::test_exhaustive, message::ExhaustiveMessages};
+/// use checksum_tapestry::fletcher16::Fletcher16;
+///
+/// // Fletcher-16 sums modulo 255, so only 255^2 outputs are reachable
+/// let mut fletcher16 = Fletcher16::default();
+/// let coverage = test_exhaustive(&mut fletcher16, &mut ExhaustiveMessages::new(2)).unwrap();
+/// assert_eq!(coverage.num_samples, 65536);
+/// assert_eq!(coverage.distinct, 255 * 255);
+/// ```
+pub fn test_exhaustive<T: OutputWidth>(
+    checksum: &mut dyn Checksum<T>,
+    source: &mut ExhaustiveMessages,
+) -> Option<Coverage> {
+    let mut bitmap = OutputBitmap::new(T::BITS)?;
+    let num_messages = source.num_messages();
+    if num_messages == 0 {
+        return None;
+    }
+
+    let mut message: Vec<u8> = Vec::new();
+    for _ in 0..num_messages {
+        message.resize(source.next_length(), 0);
+        source.fill(&mut message);
+
+        bitmap.insert(checksum.compute(&message).to_u64());
+        checksum.reset();
+    }
+
+    Some(coverage(bitmap.num_samples, bitmap.distinct, T::BITS))
+}
+
+/// Compare the distinct outputs of num_samples samples with an ideal
+/// uniform function
+fn coverage(num_samples: u64, distinct: u64, bits: u32) -> Coverage {
+    let collisions = num_samples - distinct;
+
+    Coverage {
+        num_samples,
+        distinct,
+        collisions,
+        coverage: distinct as f64 / f64::powi(2.0, bits as i32),
+        expected_distinct: expected_distinct(num_samples, bits),
+        expected_collisions: expected_collisions(num_samples, bits),
+        p_value: p_value(collisions, num_samples, bits),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        coverage::{
+            collisions_variance, distinct_outputs, expected_collisions, expected_distinct, p_value,
+            test, test_exhaustive, OutputBitmap,
+        },
+        experiment::Experiment,
+        message::ExhaustiveMessages,
+        sample::Sample,
+    };
+    use checksum_tapestry::fletcher16::Fletcher16;
+
+    /// Both counting methods give the same answer
+    #[test]
+    fn distinct_outputs_works() {
+        // Small enough for the bitmap
+        let samples = (0..=255u8)
+            .chain(0..10)
+            .map(|d| Sample { sample: d })
+            .collect();
+        let experiment: Experiment<u8> = Experiment::from_samples(samples);
+        assert_eq!(distinct_outputs(&experiment), 256);
+
+        // Too sparse for the bitmap
+        let samples = [5u32, 0xFFFF_FFFF, 5, 0]
+            .iter()
+            .map(|d| Sample { sample: *d })
+            .collect();
+        let experiment: Experiment<u32> = Experiment::from_samples(samples);
+        assert_eq!(distinct_outputs(&experiment), 3);
+    }
+
+    /// Test expected values against the birthday formulas computed
+    /// with mpmath
+    #[test]
+    fn expected_values_work() {
+        assert!(f64::abs(expected_distinct(1000, 16) - 992.416_776_936) < 1.0e-6);
+        assert!(f64::abs(expected_collisions(1000, 16) - 7.583_223_064) < 1.0e-6);
+        assert!(f64::abs(expected_collisions(65536, 16) - 24_109.163_115_7) < 1.0e-4);
+        assert!(f64::abs(expected_collisions(1_000_000, 32) - 116.406_170_946) < 1.0e-4);
+
+        assert!(f64::abs(collisions_variance(1000, 16) - 7.430_688_143) < 1.0e-6);
+        assert!(f64::abs(collisions_variance(65536, 16) - 6_370.699_875_6) < 1.0e-3);
+        assert!(f64::abs(collisions_variance(1_000_000, 32) - 116.370_039_369) < 1.0e-4);
+    }
+
+    /// Test p-values in the Poisson and normal regions
+    #[test]
+    fn p_value_works() {
+        assert!(f32::abs(p_value(10, 1000, 16) - 0.233_194) < 0.00001);
+        assert_eq!(p_value(0, 1000, 16), 1.0);
+        assert!(f32::abs(p_value(24_109, 65536, 16) - 0.5033) < 0.0001);
+        assert!(p_value(24_400, 65536, 16) < 0.0002);
+    }
+
+    /// Fletcher-16 of single bytes hits 255 outputs, with one
+    /// collision between 0x00 and 0xFF
+    #[test]
+    fn test_works_fletcher16() {
+        let mut fletcher16 = Fletcher16::default();
+        let experiment = Experiment::<u16>::run_exhaustive(&mut fletcher16, 1);
+        let coverage = test(&experiment).unwrap();
+
+        assert_eq!(coverage.num_samples, 256);
+        assert_eq!(coverage.distinct, 255);
+        assert_eq!(coverage.collisions, 1);
+        assert!(f64::abs(coverage.coverage - 255.0 / 65536.0) < 1.0e-12);
+    }
+
+    /// Counting as outputs are produced agrees with keeping every
+    /// sample
+    #[test]
+    fn test_exhaustive_works() {
+        let mut fletcher16 = Fletcher16::default();
+        let experiment = Experiment::<u16>::run_exhaustive_up_to(&mut fletcher16, 2);
+        let expected = test(&experiment).unwrap();
+
+        let coverage = test_exhaustive(&mut fletcher16, &mut ExhaustiveMessages::up_to(2)).unwrap();
+        assert_eq!(coverage.num_samples, expected.num_samples);
+        assert_eq!(coverage.distinct, expected.distinct);
+        assert_eq!(coverage.p_value, expected.p_value);
+
+        let mut bitmap = OutputBitmap::new(8).unwrap();
+        assert!(bitmap.insert(255));
+        assert!(!bitmap.insert(255));
+        assert_eq!(bitmap.distinct, 1);
+        assert!(OutputBitmap::new(33).is_none());
+    }
+
+    /// Empty experiments can't be tested
+    #[test]
+    fn test_empty_fails() {
+        let experiment: Experiment<u32> = Experiment::from_samples(vec![]);
+
+        assert!(test(&experiment).is_none());
+    }
+}
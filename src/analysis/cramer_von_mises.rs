@@ -0,0 +1,224 @@
+//! Cramér–von Mises test
+//! Perform a Cramér–von Mises goodness of fit test on a data set
+//! This can be used to find if a set of values comes from a given
+//! distribution.
+//! The Cramér–von Mises statistic integrates the squared difference
+//! between the empirical and expected CDFs, so it picks up small
+//! differences spread over the whole range that Kolmogorov–Smirnov
+//! can miss.
+//!
+//! The p-values are for a fully specified distribution, where no
+//! parameters were estimated from the data.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::{distribution::Cdf, experiment::Experiment};
+
+/// The result of a Cramér–von Mises test
+#[derive(Debug)]
+pub struct CramerVonMisesTest {
+    /// The Cramér–von Mises W^2 statistic
+    pub statistic: f32,
+    /// The probability of seeing a statistic at least this large if
+    /// the data follows the expected distribution
+    pub p_value: f32,
+}
+
+/// Calculate the Cramér–von Mises W^2 statistic
+///
+/// W^2 = 1 / (12n) + sum_{i=1}^{n} (F(x_i) - (2i - 1) / (2n))^2
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{
+///     cramer_von_mises::statistic,
+///     distribution::DiscreteUniformDistributionParameters,
+///     experiment::Experiment,
+///     sample::Sample,
+/// };
+///
+/// let data: [f32; 8] = [1.41, 0.26, 1.97, 0.33, 0.55, 0.77, 1.46, 1.18];
+///
+/// let samples: Vec<Sample<f32>> = data.iter().map(|d| Sample { sample: *d }).collect();
+///
+/// let experiment: Experiment<f32> = Experiment::from_samples(samples);
+/// let parameters = DiscreteUniformDistributionParameters { a: 0, b: 2 };
+/// let statistic = statistic(&experiment, &parameters);
+/// assert!(f32::abs(statistic - 0.02977) < 0.00001);
+/// ```
+pub fn statistic<C: Cdf + ?Sized>(experiment: &Experiment<f32>, cdf: &C) -> f32 {
+    let mut u: Vec<f64> = experiment
+        .samples
+        .iter()
+        .map(|s| cdf.cdf(s.sample) as f64)
+        .collect();
+    u.sort_by(|a, b| a.total_cmp(b));
+
+    let n = u.len() as f64;
+    let mut sum: f64 = 1.0 / (12.0 * n);
+    for (i, item) in u.iter().enumerate() {
+        let difference = item - (2 * i + 1) as f64 / (2.0 * n);
+        sum += difference * difference;
+    }
+
+    sum as f32
+}
+
+/// Compute the p-value for a Cramér–von Mises statistic with n
+/// samples
+///
+/// The statistic is adjusted with Stephens' modification
+/// W*^2 = (W^2 - 0.4/n + 0.6/n^2)(1 + 1/n), which makes the
+/// asymptotic distribution usable for small n.
+///
+/// Stephens, M. A. (1970). Use of the Kolmogorov-Smirnov,
+/// Cramer-Von Mises and Related Statistics Without Extensive Tables.
+/// Journal of the Royal Statistical Society, Series B, 32(1), 115–122.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::cramer_von_mises::p_value;
+///
+/// // The asymptotic five percent point is 0.461
+/// let p = p_value(0.461, 1000);
+/// assert!(f32::abs(p - 0.05) < 0.001);
+/// ```
+pub fn p_value(statistic: f32, n: u32) -> f32 {
+    if n == 0 {
+        return 1.0;
+    }
+
+    let n = n as f64;
+    let modified = (statistic as f64 - 0.4 / n + 0.6 / (n * n)) * (1.0 + 1.0 / n);
+
+    // The series alternates slowly for small values, and the tail
+    // probability is one to well within f32 precision there
+    if modified < 0.006 {
+        return 1.0;
+    }
+
+    asymptotic_sf(modified).clamp(0.0, 1.0) as f32
+}
+
+/// The upper tail of the limiting distribution of W^2 as n goes to
+/// infinity
+///
+/// This is Smirnov's series, computed directly so small p-values
+/// aren't lost to rounding:
+/// 1 - F(x) = 1 / pi * sum_{k=1}^{inf} (-1)^(k+1) integral from
+///            ((2k - 1) pi)^2 to (2k pi)^2 of
+///            sqrt(-sqrt(y) / sin(sqrt(y))) * e^(-xy/2) / y dy
+///
+/// Each integral is taken with y = a + (b - a)(1 - cos t) / 2, which
+/// removes the singularities at the ends and leaves a smooth periodic
+/// integrand, so the midpoint rule converges quickly.
+fn asymptotic_sf(x: f64) -> f64 {
+    const STEPS: usize = 64;
+
+    let pi = std::f64::consts::PI;
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+
+    for k in 1..=100 {
+        let a = ((2 * k - 1) as f64 * pi).powi(2);
+        let b = ((2 * k) as f64 * pi).powi(2);
+        let integral: f64 = (0..STEPS)
+            .map(|i| {
+                let t = (i as f64 + 0.5) * pi / STEPS as f64;
+                let y = a + (b - a) * (1.0 - f64::cos(t)) / 2.0;
+                let root = f64::sqrt(y);
+                f64::sqrt(-root / f64::sin(root)) * f64::exp(-x * y / 2.0) / y * (b - a) / 2.0
+                    * f64::sin(t)
+            })
+            .sum::<f64>()
+            * pi
+            / STEPS as f64;
+
+        sum += sign * integral;
+        if integral <= 1.0e-16 * f64::abs(sum) {
+            break;
+        }
+        sign = -sign;
+    }
+
+    sum / pi
+}
+
+/// Perform a Cramér–von Mises test on an experiment
+///
+/// Returns None if the experiment is empty.
+pub fn test<C: Cdf + ?Sized>(experiment: &Experiment<f32>, cdf: &C) -> Option<CramerVonMisesTest> {
+    if experiment.samples.is_empty() {
+        return None;
+    }
+
+    let statistic = statistic(experiment, cdf);
+    let n: u32 = experiment.samples.len().try_into().ok()?;
+
+    Some(CramerVonMisesTest {
+        statistic,
+        p_value: p_value(statistic, n),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        cramer_von_mises::{p_value, statistic, test},
+        distribution::DiscreteUniformDistributionParameters,
+        experiment::Experiment,
+        sample::Sample,
+    };
+
+    /// PennState STAT 415 Introduction to Mathematical Statistics
+    /// Kolmogorov–Smirnov example data, tested with Cramér–von Mises
+    #[test]
+    fn statistic_works_psu() {
+        let data: [f32; 8] = [1.41, 0.26, 1.97, 0.33, 0.55, 0.77, 1.46, 1.18];
+
+        let samples: Vec<Sample<f32>> = data.iter().map(|d| Sample { sample: *d }).collect();
+
+        let experiment: Experiment<f32> = Experiment::from_samples(samples);
+        let parameters = DiscreteUniformDistributionParameters { a: 0, b: 2 };
+
+        let statistic = statistic(&experiment, &parameters);
+        assert!(f32::abs(statistic - 0.02977) < 0.00001);
+
+        // The modified statistic is negative, the fit is as good as it gets
+        let result = test(&experiment, &parameters).unwrap();
+        assert_eq!(result.p_value, 1.0);
+    }
+
+    /// Test p-values against the asymptotic distribution computed with
+    /// mpmath
+    #[test]
+    fn p_value_works() {
+        assert!(f32::abs(p_value(0.461, 1000) - 0.050_089) < 0.00001);
+        assert!(f32::abs(p_value(0.2, 10) - 0.303_876) < 0.00001);
+        assert!(f32::abs(p_value(1.2, 50) - 0.000_773) < 0.00001);
+    }
+
+    /// Tiny statistics are as good a fit as it gets, and large ones
+    /// keep their precision in the far tail
+    #[test]
+    fn p_value_works_tails() {
+        assert_eq!(p_value(0.00045, 1000), 1.0);
+        assert_eq!(p_value(0.0, 1000), 1.0);
+        assert!(f32::abs(p_value(0.01, 1_000_000) - 0.999_994) < 0.000_001);
+
+        // 3.053_929e-12 from mpmath
+        let p = p_value(5.0, 1_000_000);
+        assert!(f32::abs(p / 3.053_929e-12 - 1.0) < 0.001);
+    }
+
+    /// Empty experiments can't be tested
+    #[test]
+    fn test_empty_fails() {
+        let experiment: Experiment<f32> = Experiment::from_samples(vec![]);
+        let parameters = DiscreteUniformDistributionParameters { a: 0, b: 2 };
+
+        assert!(test(&experiment, &parameters).is_none());
+    }
+}
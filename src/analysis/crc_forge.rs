@@ -0,0 +1,340 @@
+//! CRC forging
+//! Change a message so its CRC is any chosen value, by appending bytes
+//! or overwriting bytes at any offset.
+//!
+//! A CRC isn't a message authentication code.  It's linear, so for
+//! messages of the same length, flipping a bit always flips the same
+//! bits of the checksum whatever the rest of the message is.  The
+//! effect of each bit of the forged bytes is measured by computing the
+//! checksum with that bit flipped, then the bits that give the target
+//! are found by solving linear equations over GF(2).  Width bits are
+//! enough for any target when the generator has a +1 term, so the
+//! forged bytes are the width rounded up to whole bytes.
+//!
+//! Every forgery is checked by computing its checksum before it's
+//! returned.
+//!
+//! Stigge, M., Plötz, H., Müller, W., & Redlich, J.-P. (2006).
+//! Reversing CRC - Theory and Practice. HU Berlin Public Report
+//! SAR-PR-2006-05.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::{crc_polynomial::CrcWidth, crc_solver::solve_linear};
+use checksum_tapestry::crc::CRCConfiguration;
+
+/// A message changed to have a chosen checksum
+#[derive(Debug, PartialEq)]
+pub struct Forgery<T> {
+    /// The forged message
+    pub message: Vec<u8>,
+    /// The offset of the forged bytes in the message
+    pub offset: usize,
+    /// The forged bytes
+    pub patch: Vec<u8>,
+    /// The checksum of the forged message
+    pub checksum: T,
+}
+
+/// Find the bytes at offset in message that give the target checksum,
+/// overwriting what's there
+fn forge<T: CrcWidth>(
+    configuration: CRCConfiguration<T>,
+    mut message: Vec<u8>,
+    offset: usize,
+    target: T,
+) -> Option<Forgery<T>> {
+    let width = configuration.width as u32;
+    let num_bytes = width.div_ceil(8) as usize;
+    let num_unknowns = 8 * num_bytes as u32;
+    if offset
+        .checked_add(num_bytes)
+        .is_none_or(|end| end > message.len())
+        || target.to_u64() >> width != 0
+    {
+        return None;
+    }
+
+    let mut crc = T::crc(configuration);
+    message[offset..offset + num_bytes].fill(0);
+    let base = crc.compute(&message).to_u64();
+
+    // The checksum bits each forged bit flips
+    let columns: Vec<u64> = (0..num_unknowns as usize)
+        .map(|bit| {
+            message[offset + bit / 8] ^= 1 << (bit % 8);
+            let flipped = crc.compute(&message).to_u64();
+            message[offset + bit / 8] ^= 1 << (bit % 8);
+            flipped ^ base
+        })
+        .collect();
+
+    let rhs = target.to_u64() ^ base;
+    let mut rows: Vec<u64> = (0..width)
+        .map(|bit| {
+            let coefficients = columns
+                .iter()
+                .enumerate()
+                .fold(0, |row, (j, column)| row | (((column >> bit) & 1) << j));
+            coefficients | (((rhs >> bit) & 1) << num_unknowns)
+        })
+        .collect();
+    let (solution, _) = solve_linear(&mut rows, num_unknowns)?;
+
+    let patch: Vec<u8> = (0..num_bytes)
+        .map(|i| (solution >> (8 * i)) as u8)
+        .collect();
+    message[offset..offset + num_bytes].copy_from_slice(&patch);
+    let checksum = crc.compute(&message);
+    if checksum.to_u64() != target.to_u64() {
+        return None;
+    }
+
+    Some(Forgery {
+        message,
+        offset,
+        patch,
+        checksum,
+    })
+}
+
+/// Append bytes to a message so its checksum is target
+///
+/// The number of bytes appended is the width of the CRC rounded up to
+/// whole bytes.  The forgery is checked with the same CRC type as the
+/// configuration, so a `CRCConfiguration<u16>` forges a `CRC<u16>`
+/// checksum.
+///
+/// Returns None if the target is wider than the CRC, or no bytes give
+/// the target, which only happens when the generator has no +1 term.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::crc_forge::append;
+/// use checksum_tapestry::{
+///     crc::{BitOrder, BitWidth, CRCConfiguration, CRC},
+///     Checksum,
+/// };
+///
+/// let configuration = CRCConfiguration::<u32>::new(
+///     "CRC-32/ISO-HDLC",
+///     BitWidth::ThirtyTwo,
+///     BitOrder::LSBFirst,
+///     0x04C11DB7,
+///     true,
+///     Some(0xFFFFFFFF),
+///     Some(0xFFFFFFFF),
+/// );
+/// let forgery = append(configuration, b"Pay Bob $100", 0xCBF43926).unwrap();
+/// assert_eq!(forgery.patch.len(), 4);
+///
+/// // The same checksum as "123456789"
+/// let mut crc = CRC::<u32>::new(configuration, true);
+/// assert_eq!(crc.compute(&forgery.message), crc.compute(b"123456789"));
+/// assert!(forgery.message.starts_with(b"Pay Bob $100"));
+///
+/// let configuration = CRCConfiguration::<u16>::new(
+///     "CRC-16/ARC",
+///     BitWidth::Sixteen,
+///     BitOrder::LSBFirst,
+///     0x8005,
+///     true,
+///     None,
+///     None,
+/// );
+/// let forgery = append(configuration, b"Pay Bob $100", 0xBB3D).unwrap();
+/// let mut crc = CRC::<u16>::new(configuration, true);
+/// assert_eq!(crc.compute(&forgery.message), 0xBB3D);
+/// ```
+pub fn append<T: CrcWidth>(
+    configuration: CRCConfiguration<T>,
+    message: &[u8],
+    target: T,
+) -> Option<Forgery<T>> {
+    let num_bytes = (configuration.width as u32).div_ceil(8) as usize;
+    let mut forged = message.to_vec();
+    forged.resize(message.len() + num_bytes, 0);
+
+    forge(configuration, forged, message.len(), target)
+}
+
+/// Overwrite bytes of a message starting at offset so its checksum is
+/// target
+///
+/// The number of bytes overwritten is the width of the CRC rounded up
+/// to whole bytes, and the message keeps its length.
+///
+/// Returns None if the bytes don't fit in the message, the target is
+/// wider than the CRC, or no bytes give the target.
+pub fn patch<T: CrcWidth>(
+    configuration: CRCConfiguration<T>,
+    message: &[u8],
+    offset: usize,
+    target: T,
+) -> Option<Forgery<T>> {
+    forge(configuration, message.to_vec(), offset, target)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::crc_forge::{append, patch};
+    use checksum_tapestry::{
+        crc::{BitOrder, BitWidth, CRCConfiguration, CRC},
+        Checksum,
+    };
+
+    fn crc_32() -> CRCConfiguration<'static, u32> {
+        CRCConfiguration::<u32>::new(
+            "CRC-32/ISO-HDLC",
+            BitWidth::ThirtyTwo,
+            BitOrder::LSBFirst,
+            0x04C11DB7,
+            true,
+            Some(0xFFFFFFFF),
+            Some(0xFFFFFFFF),
+        )
+    }
+
+    #[test]
+    fn append_works() {
+        let configurations = [
+            crc_32(),
+            CRCConfiguration::<u32>::new(
+                "CRC-16/ARC",
+                BitWidth::Sixteen,
+                BitOrder::LSBFirst,
+                0x8005,
+                true,
+                None,
+                None,
+            ),
+            CRCConfiguration::<u32>::new(
+                "CRC-12/TEST",
+                BitWidth::Twelve,
+                BitOrder::MSBFirst,
+                0x80F,
+                true,
+                Some(0x5A5),
+                Some(0x3C3),
+            ),
+            CRCConfiguration::<u32>::new(
+                "CRC-3/GSM",
+                BitWidth::Three,
+                BitOrder::MSBFirst,
+                0x3,
+                false,
+                None,
+                Some(0x7),
+            ),
+        ];
+
+        for configuration in configurations {
+            let mask = u32::MAX >> (32 - configuration.width as u32);
+            let mut crc = CRC::<u32>::new(configuration, true);
+            for target in [0, 0x1234_5678 & mask, mask] {
+                let forgery = append(configuration, b"123456789", target).unwrap();
+                assert_eq!(forgery.checksum, target);
+                assert_eq!(crc.compute(&forgery.message), target);
+                assert_eq!(forgery.offset, 9);
+                assert_eq!(forgery.message[..9], *b"123456789");
+                assert_eq!(forgery.message[9..], forgery.patch[..]);
+            }
+        }
+    }
+
+    /// A 16-bit configuration is forged and checked with a 16-bit CRC
+    #[test]
+    fn append_works_u16() {
+        let configurations = [
+            CRCConfiguration::<u16>::new(
+                "CRC-16/ARC",
+                BitWidth::Sixteen,
+                BitOrder::LSBFirst,
+                0x8005,
+                true,
+                None,
+                None,
+            ),
+            CRCConfiguration::<u16>::new(
+                "CRC-16/IBM-3740",
+                BitWidth::Sixteen,
+                BitOrder::MSBFirst,
+                0x1021,
+                false,
+                Some(0xFFFF),
+                None,
+            ),
+            CRCConfiguration::<u16>::new(
+                "CRC-8/SMBUS",
+                BitWidth::Eight,
+                BitOrder::MSBFirst,
+                0x07,
+                false,
+                None,
+                None,
+            ),
+        ];
+
+        for configuration in configurations {
+            let mask = u16::MAX >> (16 - configuration.width as u32);
+            let mut crc = CRC::<u16>::new(configuration, true);
+            for target in [0, 0x1234 & mask, mask] {
+                let forgery = append(configuration, b"123456789", target).unwrap();
+                assert_eq!(forgery.checksum, target);
+                assert_eq!(crc.compute(&forgery.message), target);
+                assert_eq!(forgery.message[..9], *b"123456789");
+
+                let forgery = patch(configuration, b"123456789", 3, target).unwrap();
+                assert_eq!(crc.compute(&forgery.message), target);
+                assert_eq!(forgery.message.len(), 9);
+            }
+        }
+
+        let crc_8 = configurations[2];
+        assert!(append(crc_8, b"123456789", 0x100).is_none());
+    }
+
+    #[test]
+    fn append_works_empty() {
+        let forgery = append(crc_32(), b"", 0xDEADBEEF).unwrap();
+
+        assert_eq!(forgery.message.len(), 4);
+        assert_eq!(forgery.checksum, 0xDEADBEEF);
+    }
+
+    #[test]
+    fn patch_works() {
+        let message = b"The quick brown fox jumps over the lazy dog";
+        let mut crc = CRC::<u32>::new(crc_32(), true);
+
+        for offset in [0, 10, message.len() - 4] {
+            let forgery = patch(crc_32(), message, offset, 0xCAFEF00D).unwrap();
+            assert_eq!(crc.compute(&forgery.message), 0xCAFEF00D);
+            assert_eq!(forgery.message.len(), message.len());
+            assert_eq!(forgery.message[..offset], message[..offset]);
+            assert_eq!(forgery.message[offset + 4..], message[offset + 4..]);
+        }
+    }
+
+    #[test]
+    fn patch_fails() {
+        let message = b"123456789";
+
+        assert!(patch(crc_32(), message, 6, 0).is_none());
+        assert!(patch(crc_32(), b"", 0, 0).is_none());
+        assert!(patch(crc_32(), b"1234", usize::MAX, 0).is_none());
+
+        let crc_16 = CRCConfiguration::<u32>::new(
+            "CRC-16/ARC",
+            BitWidth::Sixteen,
+            BitOrder::LSBFirst,
+            0x8005,
+            true,
+            None,
+            None,
+        );
+        assert!(patch(crc_16, message, 0, 0x1_0000).is_none());
+        assert!(append(crc_16, message, 0x1_0000).is_none());
+    }
+}
@@ -0,0 +1,441 @@
+//! CRC polynomial notations and properties
+//! Convert a CRC polynomial between the common notations, and report
+//! the properties of its generator and the errors it always detects.
+//!
+//! The same generator is written four ways:
+//!
+//! - Normal: the coefficients below x^width, highest power in the top
+//!   bit, as in [`CRCConfiguration`].  CRC-32 is 0x04C11DB7.
+//! - Reversed: the normal value with its bits reversed, used by
+//!   LSB-first table implementations.  CRC-32 is 0xEDB88320.
+//! - Reciprocal: the normal value of the reciprocal polynomial
+//!   x^width G(1/x), which has the same error detection.  CRC-32 is
+//!   0xDB710641.
+//! - Koopman: the coefficients above x^0, highest power in the top
+//!   bit, as in Koopman's tables.  CRC-32 is 0x82608EDB.
+//!
+//! The guarantees follow from the factors of the generator: a +1 term
+//! means every burst up to the width is detected, a factor of x + 1
+//! means every odd number of bit errors is detected, and every pair
+//! of bit errors closer together than the period is detected.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use std::fmt::{Display, Formatter, Result};
+
+use crate::analysis::{experiment::OutputWidth, gf2::Polynomial};
+use checksum_tapestry::{
+    crc::{BitWidth, CRCConfiguration, Width, CRC},
+    Checksum,
+};
+
+/// A notation for writing a CRC polynomial as an integer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Notation {
+    /// The coefficients below x^width, highest power in the top bit
+    Normal,
+    /// The normal notation with the bits reversed
+    Reversed,
+    /// The normal notation of the reciprocal polynomial
+    Reciprocal,
+    /// The coefficients above x^0, highest power in the top bit
+    Koopman,
+}
+
+/// Reverse the lowest width bits of a value
+pub(crate) fn reverse(value: u64, width: u32) -> u64 {
+    value.reverse_bits() >> (64 - width)
+}
+
+/// The [`BitWidth`] for a width in bits, or None if checksum-tapestry
+/// doesn't support it
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::crc_polynomial::bit_width;
+///
+/// assert!(bit_width(16).is_some());
+/// assert!(bit_width(24).is_none());
+/// ```
+pub fn bit_width(width: u32) -> Option<BitWidth> {
+    match width {
+        3 => Some(BitWidth::Three),
+        4 => Some(BitWidth::Four),
+        7 => Some(BitWidth::Seven),
+        8 => Some(BitWidth::Eight),
+        12 => Some(BitWidth::Twelve),
+        16 => Some(BitWidth::Sixteen),
+        32 => Some(BitWidth::ThirtyTwo),
+        _ => None,
+    }
+}
+
+/// A CRC register type checksum-tapestry computes CRCs with, u16 or
+/// u32
+///
+/// checksum-tapestry has a separate [`CRC`] constructor for each
+/// register type, so this lets a function take a configuration of
+/// either type.
+pub trait CrcWidth: Width + OutputWidth {
+    /// Create a CRC with a configuration, using a lookup table
+    fn crc<'a>(configuration: CRCConfiguration<'a, Self>) -> Box<dyn Checksum<Self> + 'a>;
+}
+
+impl CrcWidth for u16 {
+    fn crc<'a>(configuration: CRCConfiguration<'a, u16>) -> Box<dyn Checksum<u16> + 'a> {
+        Box::new(CRC::<u16>::new(configuration, true))
+    }
+}
+
+impl CrcWidth for u32 {
+    fn crc<'a>(configuration: CRCConfiguration<'a, u32>) -> Box<dyn Checksum<u32> + 'a> {
+        Box::new(CRC::<u32>::new(configuration, true))
+    }
+}
+
+/// The generator polynomial written as value in a notation, including
+/// the x^width term
+///
+/// # Panics
+///
+/// Panics if width is zero or greater than 64.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{
+///     crc_polynomial::{to_generator, Notation},
+///     gf2::Polynomial,
+/// };
+///
+/// let crc32 = Polynomial(0x1_04C1_1DB7);
+/// assert_eq!(to_generator(0x82608EDB, 32, Notation::Koopman), crc32);
+/// assert_eq!(to_generator(0xEDB88320, 32, Notation::Reversed), crc32);
+/// ```
+pub fn to_generator(value: u64, width: u32, notation: Notation) -> Polynomial {
+    assert!((1..=64).contains(&width), "width must be from 1 to 64");
+    let top = 1u128 << width;
+    let value = value as u128;
+
+    match notation {
+        Notation::Normal => Polynomial(top | value),
+        Notation::Reversed => Polynomial(top | reverse(value as u64, width) as u128),
+        // The reciprocal reverses all width + 1 coefficients, so the
+        // +1 term of the reciprocal is the top term of the generator
+        Notation::Reciprocal => Polynomial(1 | (reverse(value as u64, width) as u128) << 1),
+        Notation::Koopman => Polynomial((value << 1) | 1),
+    }
+}
+
+/// Write a generator polynomial of the given width as a value in a
+/// notation
+///
+/// The generator's coefficients at and above x^width are ignored,
+/// apart from the x^width term in the reciprocal notation.
+///
+/// # Panics
+///
+/// Panics if width is zero or greater than 64.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{
+///     crc_polynomial::{from_generator, Notation},
+///     gf2::Polynomial,
+/// };
+///
+/// let crc32 = Polynomial(0x1_04C1_1DB7);
+/// assert_eq!(from_generator(crc32, 32, Notation::Normal), 0x04C11DB7);
+/// assert_eq!(from_generator(crc32, 32, Notation::Reciprocal), 0xDB710641);
+/// ```
+pub fn from_generator(generator: Polynomial, width: u32, notation: Notation) -> u64 {
+    assert!((1..=64).contains(&width), "width must be from 1 to 64");
+    let mask = u64::MAX >> (64 - width);
+    let normal = generator.0 as u64 & mask;
+
+    match notation {
+        Notation::Normal => normal,
+        Notation::Reversed => reverse(normal, width),
+        Notation::Reciprocal => reverse((generator.0 >> 1) as u64 & mask, width),
+        Notation::Koopman => (generator.0 >> 1) as u64 & mask,
+    }
+}
+
+/// The notations, factors and error detection guarantees of a CRC
+/// polynomial
+#[derive(Debug)]
+pub struct CrcPolynomialReport {
+    /// The width of the CRC in bits, the degree of the generator
+    pub width: u32,
+    /// The generator polynomial, including the x^width term
+    pub generator: Polynomial,
+    /// The polynomial in normal notation
+    pub normal: u64,
+    /// The polynomial in reversed notation
+    pub reversed: u64,
+    /// The polynomial in reciprocal notation
+    pub reciprocal: u64,
+    /// The polynomial in Koopman notation
+    pub koopman: u64,
+    /// The irreducible factors of the generator and their
+    /// multiplicities, lowest degree first
+    pub factors: Vec<(Polynomial, u32)>,
+    /// Whether the generator is irreducible
+    pub irreducible: bool,
+    /// Whether the generator is primitive
+    pub primitive: bool,
+    /// The period of the generator, the smallest e where it divides
+    /// x^e + 1, or None if it has no +1 term
+    pub period: Option<u128>,
+    /// Whether x + 1 divides the generator, so every odd number of bit
+    /// errors is detected
+    pub divisible_by_x_plus_one: bool,
+    /// The longest burst that is always detected, in bits
+    pub max_detected_burst: u32,
+    /// The longest codeword, data and CRC, in which every double bit
+    /// error is detected, or None if it has no +1 term
+    pub max_double_error_length: Option<u128>,
+}
+
+impl CrcPolynomialReport {
+    /// Build a report on the polynomial of a CRC configuration
+    ///
+    /// Only the polynomial and width of the configuration are used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tapestry_analysis::analysis::crc_polynomial::CrcPolynomialReport;
+    /// use checksum_tapestry::crc::{BitOrder, BitWidth, CRCConfiguration};
+    ///
+    /// let configuration = CRCConfiguration::<u16>::new(
+    ///     "CRC-16/ARC",
+    ///     BitWidth::Sixteen,
+    ///     BitOrder::LSBFirst,
+    ///     0x8005,
+    ///     true,
+    ///     None,
+    ///     None,
+    /// );
+    /// let report = CrcPolynomialReport::new(&configuration);
+    /// println!("{report}");
+    ///
+    /// assert_eq!(report.koopman, 0xC002);
+    /// assert!(report.divisible_by_x_plus_one);
+    /// assert_eq!(report.period, Some(32767));
+    /// ```
+    pub fn new<T: Width + Into<u64>>(configuration: &CRCConfiguration<T>) -> CrcPolynomialReport {
+        let width = configuration.width as u32;
+        let generator = Polynomial::from_configuration(configuration);
+        let factors = generator.factor();
+        let period = generator.order();
+
+        // Bursts are detected up to the degree of the generator with
+        // any factors of x removed
+        let max_detected_burst = width - generator.0.trailing_zeros();
+
+        CrcPolynomialReport {
+            width,
+            generator,
+            normal: from_generator(generator, width, Notation::Normal),
+            reversed: from_generator(generator, width, Notation::Reversed),
+            reciprocal: from_generator(generator, width, Notation::Reciprocal),
+            koopman: from_generator(generator, width, Notation::Koopman),
+            irreducible: factors.len() == 1 && factors[0].1 == 1,
+            primitive: generator.is_primitive(),
+            divisible_by_x_plus_one: factors.iter().any(|(f, _)| *f == Polynomial(0b11)),
+            factors,
+            period,
+            max_detected_burst,
+            max_double_error_length: period,
+        }
+    }
+
+    /// The factors written as a product, like (x + 1)(x^15 + x + 1)
+    fn factorization(&self) -> String {
+        self.factors
+            .iter()
+            .map(|(f, m)| match m {
+                1 => format!("({f})"),
+                _ => format!("({f})^{m}"),
+            })
+            .collect()
+    }
+
+    /// The properties as pairs of names and values, in report order
+    fn properties(&self) -> Vec<(&'static str, String)> {
+        let digits = self.width.div_ceil(4) as usize;
+        let yes_no = |b: bool| String::from(if b { "yes" } else { "no" });
+        let optional = |v: Option<u128>| v.map_or(String::from("none"), |v| v.to_string());
+
+        vec![
+            ("width", self.width.to_string()),
+            ("generator", self.generator.to_string()),
+            ("normal", format!("0x{:0digits$X}", self.normal)),
+            ("reversed", format!("0x{:0digits$X}", self.reversed)),
+            ("reciprocal", format!("0x{:0digits$X}", self.reciprocal)),
+            ("koopman", format!("0x{:0digits$X}", self.koopman)),
+            ("factors", self.factorization()),
+            ("irreducible", yes_no(self.irreducible)),
+            ("primitive", yes_no(self.primitive)),
+            ("period", optional(self.period)),
+            ("divisible by x + 1", yes_no(self.divisible_by_x_plus_one)),
+            (
+                "detects odd weight errors",
+                yes_no(self.divisible_by_x_plus_one),
+            ),
+            (
+                "detects all bursts up to",
+                self.max_detected_burst.to_string(),
+            ),
+            (
+                "detects all double errors up to",
+                optional(self.max_double_error_length),
+            ),
+        ]
+    }
+
+    /// Format the report as CSV with a header line
+    ///
+    /// Values that contain commas are quoted.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("property,value\n");
+        for (name, value) in self.properties() {
+            if value.contains(',') {
+                csv.push_str(&format!("{name},\"{value}\"\n"));
+            } else {
+                csv.push_str(&format!("{name},{value}\n"));
+            }
+        }
+
+        csv
+    }
+}
+
+/// Format a report with one property on each line
+impl Display for CrcPolynomialReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let properties = self.properties();
+        let name_width = properties.iter().map(|(n, _)| n.len()).max().unwrap_or(0);
+        for (name, value) in properties {
+            writeln!(f, "{name:<name_width$}  {value}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        crc_polynomial::{from_generator, to_generator, CrcPolynomialReport, Notation},
+        gf2::Polynomial,
+    };
+    use checksum_tapestry::crc::{BitOrder, BitWidth, CRCConfiguration};
+
+    const NOTATIONS: [Notation; 4] = [
+        Notation::Normal,
+        Notation::Reversed,
+        Notation::Reciprocal,
+        Notation::Koopman,
+    ];
+
+    /// Every notation converts back to the same generator
+    #[test]
+    fn notations_round_trip() {
+        for (generator, width) in [
+            (0x1_04C1_1DB7u128, 32),
+            (0x1_8005, 16),
+            (0x107, 8),
+            (0b1011, 3),
+        ] {
+            let generator = Polynomial(generator);
+            for notation in NOTATIONS {
+                let value = from_generator(generator, width, notation);
+                assert_eq!(to_generator(value, width, notation), generator);
+            }
+        }
+    }
+
+    #[test]
+    fn report_works_crc32() {
+        let configuration = CRCConfiguration::<u32>::new(
+            "CRC-32/ISO-HDLC",
+            BitWidth::ThirtyTwo,
+            BitOrder::LSBFirst,
+            0x04C11DB7,
+            true,
+            Some(0xFFFFFFFF),
+            Some(0xFFFFFFFF),
+        );
+        let report = CrcPolynomialReport::new(&configuration);
+
+        assert_eq!(report.normal, 0x04C11DB7);
+        assert_eq!(report.reversed, 0xEDB88320);
+        assert_eq!(report.reciprocal, 0xDB710641);
+        assert_eq!(report.koopman, 0x82608EDB);
+        assert!(report.irreducible);
+        assert!(report.primitive);
+        assert!(!report.divisible_by_x_plus_one);
+        assert_eq!(report.period, Some(0xFFFFFFFF));
+        assert_eq!(report.max_detected_burst, 32);
+
+        let text = report.to_string();
+        assert!(text.contains("koopman"));
+        assert!(text.contains("0x82608EDB"));
+    }
+
+    #[test]
+    fn report_works_crc16() {
+        let configuration = CRCConfiguration::<u16>::new(
+            "CRC-16/ARC",
+            BitWidth::Sixteen,
+            BitOrder::LSBFirst,
+            0x8005,
+            true,
+            None,
+            None,
+        );
+        let report = CrcPolynomialReport::new(&configuration);
+
+        assert_eq!(report.reversed, 0xA001);
+        assert_eq!(report.reciprocal, 0x4003);
+        assert_eq!(report.koopman, 0xC002);
+        assert_eq!(
+            report.factors,
+            vec![(Polynomial(0b11), 1), (Polynomial(0x8003), 1)]
+        );
+        assert!(!report.irreducible);
+        assert!(!report.primitive);
+        assert!(report.divisible_by_x_plus_one);
+        assert_eq!(report.max_double_error_length, Some(32767));
+
+        let csv = report.to_csv();
+        assert!(csv.starts_with("property,value\n"));
+        assert!(csv.contains("factors,(x + 1)(x^15 + x + 1)\n"));
+        assert!(csv.contains("period,32767\n"));
+    }
+
+    /// A generator without a +1 term has no period and a shorter
+    /// burst guarantee
+    #[test]
+    fn report_works_even() {
+        let configuration = CRCConfiguration::<u16>::new(
+            "CRC-16/EVEN",
+            BitWidth::Sixteen,
+            BitOrder::MSBFirst,
+            0x8004,
+            false,
+            None,
+            None,
+        );
+        let report = CrcPolynomialReport::new(&configuration);
+
+        assert_eq!(report.period, None);
+        assert_eq!(report.max_detected_burst, 14);
+        assert_eq!(report.factors[0], (Polynomial::X, 2));
+        assert!(report.to_csv().contains("period,none\n"));
+    }
+}
@@ -0,0 +1,626 @@
+//! CRC parameter recovery
+//! Recover the width, polynomial, initial value, bit order and final
+//! XOR of an unknown CRC from messages and their checksums, in the
+//! style of CRC RevEng.
+//!
+//! CRCs are linear, so for two messages of the same length the XOR of
+//! their checksums is the CRC of the XOR of the messages with no
+//! initial value or final XOR.  Written as polynomials, the generator
+//! divides D(x) x^width + C(x) for every such pair, where D(x) is the
+//! XOR of the messages and C(x) the XOR of the checksums.  The
+//! greatest common divisor of these is usually the generator itself.
+//!
+//! With the generator known, the initial value is found by comparing
+//! messages of different lengths, since it's shifted by the length of
+//! the message while the final XOR isn't.  That leaves a small set of
+//! linear equations over GF(2).  Each solution is checked against
+//! every message before it's returned.
+//!
+//! Some parameters can't be told apart from the messages alone:
+//!
+//! - With only one message length, the initial value and final XOR
+//!   can't be separated, so the initial value is taken as zero.
+//! - When the generator has a factor of x + 1, several initial values
+//!   can give the same checksums, and all of them are returned.
+//!
+//! checksum-tapestry uses the bit order for both reflect in and
+//! reflect out when the bit order is least significant bit first, so
+//! CRCs that reflect their input but not their output aren't found.
+//!
+//! Cook, G. (2020). CRC RevEng. <https://reveng.sourceforge.io/>
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use std::collections::BTreeMap;
+
+use crate::analysis::{
+    crc_polynomial::{bit_width, reverse},
+    gf2::Polynomial,
+};
+use checksum_tapestry::{
+    crc::{BitOrder, CRCConfiguration, CRC},
+    Checksum,
+};
+
+/// The widths tried, every width checksum-tapestry supports
+const WIDTHS: [u32; 7] = [3, 4, 7, 8, 12, 16, 32];
+
+/// The most solutions tried for each width and bit order when the
+/// initial value is ambiguous
+pub const MAX_SOLUTIONS: usize = 16;
+
+/// The largest number of extra factors searched when the messages
+/// don't pin down the generator, as a degree
+const MAX_EXCESS_DEGREE: u32 = 16;
+
+/// The parameters of a CRC, in the Rocksoft model
+///
+/// Williams, Ross N. "A Painless Guide to CRC Error Detection
+/// Algorithms", Rocksoft Pty Ltd., 1993
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CrcParameters {
+    /// The width of the CRC in bits
+    pub width: u32,
+    /// The polynomial in normal notation, without the x^width term
+    pub poly: u32,
+    /// The initial value, not reflected
+    pub init: u32,
+    /// Whether the bits of each byte are processed least significant
+    /// first
+    pub reflect_in: bool,
+    /// Whether the final value is reflected
+    pub reflect_out: bool,
+    /// The value XORed with the final value
+    pub xor_out: u32,
+}
+
+impl CrcParameters {
+    /// The bit order for checksum-tapestry
+    pub fn bit_order(&self) -> BitOrder {
+        if self.reflect_in {
+            BitOrder::LSBFirst
+        } else {
+            BitOrder::MSBFirst
+        }
+    }
+
+    /// A configuration with these parameters, ready to use with
+    /// [`CRC`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if checksum-tapestry doesn't support the width.
+    pub fn configuration<'a>(&self, name: &'a str) -> CRCConfiguration<'a, u32> {
+        let width = bit_width(self.width).expect("checksum-tapestry doesn't support this width");
+
+        CRCConfiguration::<u32>::new(
+            name,
+            width,
+            self.bit_order(),
+            self.poly,
+            self.reflect_out,
+            Some(self.init),
+            Some(self.xor_out),
+        )
+    }
+}
+
+/// A polynomial over GF(2) of any degree, used for the message
+/// polynomials that don't fit in a [`Polynomial`]
+#[derive(Clone)]
+struct BitPolynomial(Vec<u64>);
+
+impl BitPolynomial {
+    fn zero(bits: usize) -> BitPolynomial {
+        BitPolynomial(vec![0; bits.div_ceil(64)])
+    }
+
+    fn degree(&self) -> Option<usize> {
+        self.0
+            .iter()
+            .rposition(|limb| *limb != 0)
+            .map(|i| i * 64 + self.0[i].ilog2() as usize)
+    }
+
+    fn flip(&mut self, i: usize) {
+        self.0[i / 64] ^= 1 << (i % 64);
+    }
+
+    /// Add other times x^shift
+    fn add_shifted(&mut self, other: &BitPolynomial, shift: usize) {
+        let (limbs, bits) = (shift / 64, shift % 64);
+        for (i, limb) in other.0.iter().enumerate().filter(|(_, l)| **l != 0) {
+            self.0[i + limbs] ^= limb << bits;
+            if bits > 0 && i + limbs + 1 < self.0.len() {
+                self.0[i + limbs + 1] ^= limb >> (64 - bits);
+            }
+        }
+    }
+
+    fn rem(mut self, divisor: &BitPolynomial) -> BitPolynomial {
+        let divisor_degree = divisor.degree().expect("division by the zero polynomial");
+        while let Some(degree) = self.degree() {
+            if degree < divisor_degree {
+                break;
+            }
+            self.add_shifted(divisor, degree - divisor_degree);
+        }
+
+        self
+    }
+
+    fn to_polynomial(&self) -> Option<Polynomial> {
+        match self.degree() {
+            Some(degree) if degree < 128 => Some(Polynomial(
+                self.0
+                    .iter()
+                    .take(2)
+                    .enumerate()
+                    .map(|(i, l)| (*l as u128) << (64 * i))
+                    .sum(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn gcd(a: BitPolynomial, b: BitPolynomial) -> BitPolynomial {
+    let (mut a, mut b) = (a, b);
+    while b.degree().is_some() {
+        let remainder = a.rem(&b);
+        a = b;
+        b = remainder;
+    }
+
+    a
+}
+
+/// The bits of a message in the order the CRC processes them
+fn message_bits(message: &[u8], reflect_in: bool) -> impl Iterator<Item = bool> + '_ {
+    message.iter().flat_map(move |byte| {
+        (0..8).map(move |i| {
+            let shift = if reflect_in { i } else { 7 - i };
+            (byte >> shift) & 1 == 1
+        })
+    })
+}
+
+/// The CRC register after a message, with no initial value or final
+/// XOR, M(x) x^width mod G(x)
+fn remainder(message: &[u8], reflect_in: bool, width: u32, poly: u64) -> u64 {
+    let mask = u64::MAX >> (64 - width);
+
+    message_bits(message, reflect_in).fold(0, |register, bit| {
+        let top = ((register >> (width - 1)) & 1 == 1) ^ bit;
+        let register = (register << 1) & mask;
+        if top {
+            register ^ poly
+        } else {
+            register
+        }
+    })
+}
+
+/// Solve a set of linear equations over GF(2)
+///
+/// Each row has the coefficients of the unknowns in its low bits and
+/// the right hand side in bit num_unknowns.  Returns a solution and a
+/// basis for the solutions of the homogeneous equations, or None if
+/// there's no solution.
+pub(crate) fn solve_linear(rows: &mut [u64], num_unknowns: u32) -> Option<(u64, Vec<u64>)> {
+    let mut pivots: Vec<u32> = Vec::new();
+
+    for column in 0..num_unknowns {
+        let rank = pivots.len();
+        let Some(found) = (rank..rows.len()).find(|r| (rows[*r] >> column) & 1 == 1) else {
+            continue;
+        };
+        rows.swap(rank, found);
+        let pivot = rows[rank];
+        for (r, row) in rows.iter_mut().enumerate() {
+            if r != rank && (*row >> column) & 1 == 1 {
+                *row ^= pivot;
+            }
+        }
+        pivots.push(column);
+    }
+
+    let coefficients = (1u64 << num_unknowns) - 1;
+    if rows
+        .iter()
+        .any(|row| row & coefficients == 0 && (row >> num_unknowns) & 1 == 1)
+    {
+        return None;
+    }
+
+    let mut solution: u64 = 0;
+    for (row, column) in rows.iter().zip(pivots.iter()) {
+        solution |= ((row >> num_unknowns) & 1) << column;
+    }
+
+    let basis = (0..num_unknowns)
+        .filter(|column| !pivots.contains(column))
+        .map(|free| {
+            let mut vector = 1 << free;
+            for (row, column) in rows.iter().zip(pivots.iter()) {
+                vector |= ((row >> free) & 1) << column;
+            }
+            vector
+        })
+        .collect();
+
+    Some((solution, basis))
+}
+
+/// The generators of the given width that divide g
+///
+/// Generators without a +1 term are left out, since they're a narrower
+/// CRC shifted left, and would fit whenever that CRC does.
+fn generators(g: &BitPolynomial, width: u32) -> Vec<Polynomial> {
+    let degree = match g.degree() {
+        Some(degree) if degree >= width as usize => degree as u32,
+        _ => return Vec::new(),
+    };
+    let excess = degree - width;
+    let Some(g) = g.to_polynomial().filter(|_| excess <= MAX_EXCESS_DEGREE) else {
+        return Vec::new();
+    };
+
+    // Try every cofactor of the extra degree
+    (0..(1u128 << excess))
+        .map(|low| Polynomial((1 << excess) | low))
+        .filter_map(|cofactor| {
+            let (generator, remainder) = g.div_rem(cofactor);
+            remainder.is_zero().then_some(generator)
+        })
+        .filter(|generator| generator.0 & 1 == 1)
+        .collect()
+}
+
+/// Find the initial values and final XORs for a generator, given the
+/// register value of each message less its CRC with no initial value
+///
+/// The register is I x^(8 len) + X' mod G(x), where X' is the final XOR
+/// as seen before any output reflection.
+fn init_and_xor(
+    generator: Polynomial,
+    width: u32,
+    lengths: &BTreeMap<usize, u64>,
+) -> Vec<(u64, u64)> {
+    let shift = |value: u64, length: usize| {
+        let x_length = Polynomial::X.pow_mod(8 * length as u128, generator);
+        Polynomial(value as u128).mul_mod(x_length, generator).0 as u64
+    };
+
+    let (first_length, first) = lengths.iter().next().map(|(l, r)| (*l, *r)).unwrap();
+
+    // Comparing each length with the first cancels the final XOR
+    let mut rows: Vec<u64> = Vec::new();
+    for (length, register) in lengths.iter().skip(1) {
+        let columns: Vec<u64> = (0..width)
+            .map(|j| shift(1 << j, *length) ^ shift(1 << j, first_length))
+            .collect();
+        let rhs = register ^ first;
+        for bit in 0..width {
+            let coefficients = columns
+                .iter()
+                .enumerate()
+                .fold(0, |row, (j, column)| row | (((column >> bit) & 1) << j));
+            rows.push(coefficients | (((rhs >> bit) & 1) << width));
+        }
+    }
+
+    let (solution, basis) = if rows.is_empty() {
+        (0, Vec::new())
+    } else {
+        match solve_linear(&mut rows, width) {
+            Some(solved) => solved,
+            None => return Vec::new(),
+        }
+    };
+
+    (0..(1u64 << basis.len()))
+        .take(MAX_SOLUTIONS)
+        .map(|combination| {
+            basis
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| (combination >> i) & 1 == 1)
+                .fold(solution, |init, (_, vector)| init ^ vector)
+        })
+        .map(|init| (init, first ^ shift(init, first_length)))
+        .collect()
+}
+
+/// Returns true if a CRC with these parameters gives every checksum
+fn matches(parameters: &CrcParameters, samples: &[(&[u8], u32)]) -> bool {
+    let mut crc = CRC::<u32>::new(parameters.configuration("solved"), false);
+
+    samples
+        .iter()
+        .all(|(message, checksum)| crc.compute(message) == *checksum)
+}
+
+/// Recover the parameters of a CRC from messages and their checksums
+///
+/// At least two messages of the same length are needed to find the
+/// generator, and more pairs make it more likely to be unique.
+/// Messages of at least two different lengths are needed to separate
+/// the initial value from the final XOR.
+///
+/// Returns every set of parameters that gives all the checksums,
+/// narrowest first, or an empty vector if none do.  At most
+/// [`MAX_SOLUTIONS`] initial values are tried for each generator.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::crc_solver::solve;
+/// use checksum_tapestry::{
+///     crc::{BitOrder, BitWidth, CRCConfiguration, CRC},
+///     Checksum,
+/// };
+///
+/// let mut unknown = CRC::<u16>::new(
+///     CRCConfiguration::<u16>::new(
+///         "CRC-16/CCITT-FALSE",
+///         BitWidth::Sixteen,
+///         BitOrder::MSBFirst,
+///         0x1021,
+///         false,
+///         Some(0xFFFF),
+///         None,
+///     ),
+///     true,
+/// );
+/// let messages: [&[u8]; 5] = [b"123456789", b"987654321", b"hello", b"world", b"HELLO"];
+/// let samples: Vec<(&[u8], u32)> = messages
+///     .iter()
+///     .map(|m| (*m, unknown.compute(m) as u32))
+///     .collect();
+///
+/// // 0x1021 has a factor of x + 1, so there's more than one initial
+/// // value that fits
+/// let solutions = solve(&samples);
+/// assert!(solutions.iter().all(|s| s.width == 16 && s.poly == 0x1021));
+/// let solution = solutions.iter().find(|s| s.init == 0xFFFF).unwrap();
+///
+/// // The solution is ready to use
+/// let mut crc = CRC::<u32>::new(solution.configuration("CRC-16/SOLVED"), true);
+/// assert_eq!(crc.compute(b"123456789"), 0x29B1);
+/// ```
+pub fn solve(samples: &[(&[u8], u32)]) -> Vec<CrcParameters> {
+    let max_checksum = samples.iter().map(|(_, c)| *c).max().unwrap_or(0);
+    let mut by_length: BTreeMap<usize, Vec<(&[u8], u32)>> = BTreeMap::new();
+    for (message, checksum) in samples {
+        by_length
+            .entry(message.len())
+            .or_default()
+            .push((message, *checksum));
+    }
+
+    let mut solutions: Vec<CrcParameters> = Vec::new();
+    let orders = [(false, false), (false, true), (true, true)];
+
+    for width in WIDTHS
+        .into_iter()
+        .filter(|w| max_checksum.checked_shr(*w).unwrap_or(0) == 0)
+    {
+        for (reflect_in, reflect_out) in orders {
+            let register = |checksum: u32| {
+                if reflect_out {
+                    reverse(checksum as u64, width)
+                } else {
+                    checksum as u64
+                }
+            };
+
+            // The generator divides D(x) x^width + C(x) for each pair
+            let mut g = BitPolynomial::zero(0);
+            for group in by_length.values() {
+                let (first, first_checksum) = group[0];
+                for (message, checksum) in &group[1..] {
+                    let bits = message.len() * 8;
+                    let mut difference = BitPolynomial::zero(bits + width as usize);
+                    let first_bits = message_bits(first, reflect_in);
+                    for (i, (a, b)) in first_bits
+                        .zip(message_bits(message, reflect_in))
+                        .enumerate()
+                    {
+                        if a != b {
+                            difference.flip(width as usize + bits - 1 - i);
+                        }
+                    }
+                    let c = register(first_checksum ^ checksum);
+                    for bit in (0..width as usize).filter(|b| (c >> b) & 1 == 1) {
+                        difference.flip(bit);
+                    }
+                    g = gcd(difference, g);
+                }
+            }
+
+            for generator in generators(&g, width) {
+                let poly = generator.0 as u64 & (u64::MAX >> (64 - width));
+
+                // Every message of a length should leave the same
+                // register once its CRC is removed
+                let mut lengths: BTreeMap<usize, u64> = BTreeMap::new();
+                let consistent = samples.iter().all(|(message, checksum)| {
+                    let value = register(*checksum) ^ remainder(message, reflect_in, width, poly);
+                    *lengths.entry(message.len()).or_insert(value) == value
+                });
+                if !consistent {
+                    continue;
+                }
+
+                for (init, xor) in init_and_xor(generator, width, &lengths) {
+                    let xor_out = if reflect_out {
+                        reverse(xor, width)
+                    } else {
+                        xor
+                    };
+                    let parameters = CrcParameters {
+                        width,
+                        poly: poly as u32,
+                        init: init as u32,
+                        reflect_in,
+                        reflect_out,
+                        xor_out: xor_out as u32,
+                    };
+                    if matches(&parameters, samples) && !solutions.contains(&parameters) {
+                        solutions.push(parameters);
+                    }
+                }
+            }
+        }
+    }
+
+    solutions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        crc_solver::{solve, CrcParameters},
+        message::{MessageSource, UniformMessages},
+        prng::Pcg32,
+    };
+    use checksum_tapestry::{
+        adler32::Adler32,
+        crc::{BitOrder, BitWidth, CRCConfiguration, CRC},
+        Checksum,
+    };
+
+    /// Random messages with two of each length
+    fn messages(seed: u64, lengths: &[usize]) -> Vec<Vec<u8>> {
+        let mut source = UniformMessages::new(Pcg32::new(seed), 0);
+        lengths
+            .iter()
+            .flat_map(|length| [*length, *length])
+            .map(|length| {
+                let mut message = vec![0; length];
+                source.fill(&mut message);
+                message
+            })
+            .collect()
+    }
+
+    /// Solve for the parameters of a CRC from checksums of random
+    /// messages
+    fn solve_with(crc: &mut dyn Checksum<u32>, lengths: &[usize]) -> Vec<CrcParameters> {
+        let messages = messages(1, lengths);
+        let samples: Vec<(&[u8], u32)> = messages
+            .iter()
+            .map(|m| (m.as_slice(), crc.compute(m)))
+            .collect();
+
+        solve(&samples)
+    }
+
+    #[test]
+    fn solve_works_crc32() {
+        let mut crc32 = CRC::<u32>::new(
+            CRCConfiguration::<u32>::new(
+                "CRC-32/ISO-HDLC",
+                BitWidth::ThirtyTwo,
+                BitOrder::LSBFirst,
+                0x04C11DB7,
+                true,
+                Some(0xFFFFFFFF),
+                Some(0xFFFFFFFF),
+            ),
+            true,
+        );
+        let solutions = solve_with(&mut crc32, &[9, 16, 40]);
+
+        assert_eq!(
+            solutions,
+            vec![CrcParameters {
+                width: 32,
+                poly: 0x04C11DB7,
+                init: 0xFFFFFFFF,
+                reflect_in: true,
+                reflect_out: true,
+                xor_out: 0xFFFFFFFF,
+            }]
+        );
+    }
+
+    /// A narrower CRC that reflects its output but not its input
+    #[test]
+    fn solve_works_reflect_out() {
+        let parameters = CrcParameters {
+            width: 12,
+            poly: 0x80F,
+            init: 0x5A5,
+            reflect_in: false,
+            reflect_out: true,
+            xor_out: 0x3C3,
+        };
+        let mut crc = CRC::<u32>::new(parameters.configuration("CRC-12/TEST"), true);
+        let solutions = solve_with(&mut crc, &[5, 8, 13]);
+
+        assert!(solutions.contains(&parameters));
+        assert!(solutions.iter().all(|s| s.width == 12));
+    }
+
+    /// CRC-16/ARC has a factor of x + 1, so more than one initial value
+    /// may fit, but the real one is always found
+    #[test]
+    fn solve_works_crc16_arc() {
+        let parameters = CrcParameters {
+            width: 16,
+            poly: 0x8005,
+            init: 0,
+            reflect_in: true,
+            reflect_out: true,
+            xor_out: 0,
+        };
+        let mut crc = CRC::<u32>::new(parameters.configuration("CRC-16/ARC"), true);
+        let solutions = solve_with(&mut crc, &[4, 7, 20]);
+
+        assert!(solutions.contains(&parameters));
+        assert!(solutions.iter().all(|s| s.poly == 0x8005 && s.reflect_in));
+    }
+
+    /// A reflected CRC whose initial value changes when it's
+    /// reflected, so the solution has to give it unreflected, as the
+    /// catalogue does
+    #[test]
+    fn solve_works_crc16_riello() {
+        let parameters = CrcParameters {
+            width: 16,
+            poly: 0x1021,
+            init: 0xB2AA,
+            reflect_in: true,
+            reflect_out: true,
+            xor_out: 0,
+        };
+        let mut crc = CRC::<u32>::new(parameters.configuration("CRC-16/RIELLO"), true);
+        assert_eq!(crc.compute(b"123456789"), 0x63D0);
+        let solutions = solve_with(&mut crc, &[4, 7, 20]);
+
+        assert!(solutions.contains(&parameters));
+        assert!(solutions.iter().all(|s| s.poly == 0x1021 && s.reflect_in));
+    }
+
+    /// With one message length the initial value is taken as zero
+    #[test]
+    fn solve_works_one_length() {
+        let mut crc32 = CRC::<u32>::default();
+        let solutions = solve_with(&mut crc32, &[12, 12]);
+
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].poly, 0x04C11DB7);
+        assert_eq!(solutions[0].init, 0);
+    }
+
+    /// Adler-32 isn't a CRC
+    #[test]
+    fn solve_fails_adler32() {
+        let mut adler32 = Adler32::default();
+
+        assert!(solve_with(&mut adler32, &[4, 8, 12, 16]).is_empty());
+        assert!(solve(&[]).is_empty());
+    }
+}
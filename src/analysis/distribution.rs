@@ -2,14 +2,57 @@
 //! common distributions.
 use checksum_tapestry::crc::{BitOrder, BitWidth, CRCConfiguration, CRC};
 use checksum_tapestry::Checksum;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use crate::analysis::sample::Sample;
+use crate::analysis::{
+    sample::Sample,
+    special::{ln_gamma, normal_cdf, regularized_gamma_q},
+};
 
 /// A generic distribution interface
 pub trait Distribution<T> {
     /// Sample a single item from a distribution
     fn sample(&mut self) -> Sample<T>;
+
+    /// The seed the distribution was created with, if it has one
+    ///
+    /// Experiments record this so a result can be replayed exactly.
+    fn seed(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// A mutable reference to a distribution is also a distribution, so
+/// generators can borrow a distribution instead of owning it
+impl<T, D: Distribution<T> + ?Sized> Distribution<T> for &mut D {
+    fn sample(&mut self) -> Sample<T> {
+        (**self).sample()
+    }
+
+    fn seed(&self) -> Option<u64> {
+        (**self).seed()
+    }
+}
+
+/// A cumulative distribution function
+///
+/// This is used by goodness of fit tests like Kolmogorov–Smirnov to
+/// compare samples against a model distribution.
+pub trait Cdf {
+    /// Evaluate the CDF at x, F(x) = P(X <= x)
+    fn cdf(&self, x: f32) -> f32;
+
+    /// Evaluate the left limit of the CDF at x, F(x-) = P(X < x)
+    ///
+    /// For continuous distributions this is the same as cdf.
+    /// Discrete distributions should override this so tests can
+    /// compare against both sides of each step.
+    fn cdf_left(&self, x: f32) -> f32 {
+        self.cdf(x)
+    }
 }
 
 /// Critical values for tails of distributions
@@ -22,6 +65,18 @@ pub enum CriticalValue {
     OnePercent,
 }
 
+impl CriticalValue {
+    /// The significance level alpha for this critical value, e.g. 0.05
+    /// for FivePercent
+    pub fn alpha(&self) -> f32 {
+        match self {
+            CriticalValue::TenPercent => 0.10,
+            CriticalValue::FivePercent => 0.05,
+            CriticalValue::OnePercent => 0.01,
+        }
+    }
+}
+
 /// The parameters of the discrete uniform distribution
 pub struct DiscreteUniformDistributionParameters {
     /// The a parameter of the distribution
@@ -34,6 +89,150 @@ pub struct DiscreteUniformDistributionParameters {
     pub b: u32,
 }
 
+/// The CDF of U[a; b], treated as continuous so large output spaces
+/// like u32 checksums can be tested directly
+impl Cdf for DiscreteUniformDistributionParameters {
+    fn cdf(&self, x: f32) -> f32 {
+        normalize_variable(x, self).clamp(0.0, 1.0)
+    }
+}
+
+/// The parameters of the uniform distribution over every output of
+/// an n-bit checksum, U[0; 2^bits - 1]
+///
+/// Unlike [`DiscreteUniformDistributionParameters`] this can describe
+/// 64-bit output spaces.
+pub struct UniformOutputDistributionParameters {
+    /// The width of the output in bits, from 1 to 64
+    pub bits: u32,
+}
+
+/// The CDF of U[0; 2^bits - 1], treated as continuous like
+/// [`DiscreteUniformDistributionParameters`]
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::distribution::{Cdf, UniformOutputDistributionParameters};
+///
+/// let parameters = UniformOutputDistributionParameters { bits: 8 };
+/// assert_eq!(parameters.cdf(0.0), 0.0);
+/// assert!(f32::abs(parameters.cdf(127.5) - 0.5) < 0.00001);
+/// assert_eq!(parameters.cdf(255.0), 1.0);
+/// ```
+impl Cdf for UniformOutputDistributionParameters {
+    fn cdf(&self, x: f32) -> f32 {
+        let max = (u64::MAX >> (64 - self.bits)) as f64;
+
+        (x as f64 / max).clamp(0.0, 1.0) as f32
+    }
+}
+
+/// The parameters of the Poisson distribution
+///
+/// This is the distribution of the number of events in a fixed
+/// interval, such as the number of collisions in a hash bucket.
+pub struct PoissonDistributionParameters {
+    /// The mean number of events, lambda
+    pub lambda: f32,
+}
+
+/// The CDF of the Poisson distribution
+///
+/// P(X <= k) is the regularized upper incomplete gamma function
+/// Q(k + 1, lambda).
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::distribution::{Cdf, PoissonDistributionParameters};
+///
+/// let parameters = PoissonDistributionParameters { lambda: 2.0 };
+/// // P(X <= 0) = e^-2
+/// assert!(f32::abs(parameters.cdf(0.0) - f32::exp(-2.0)) < 0.00001);
+/// // P(X < 1) = P(X <= 0)
+/// assert_eq!(parameters.cdf_left(1.0), parameters.cdf(0.0));
+/// ```
+impl Cdf for PoissonDistributionParameters {
+    fn cdf(&self, x: f32) -> f32 {
+        if x < 0.0 {
+            return 0.0;
+        }
+        let k = x.floor() as f64;
+
+        regularized_gamma_q(k + 1.0, self.lambda as f64) as f32
+    }
+
+    fn cdf_left(&self, x: f32) -> f32 {
+        self.cdf(x.ceil() - 1.0)
+    }
+}
+
+/// The parameters of the Irwin–Hall distribution
+///
+/// This is the distribution of the sum of n independent U(0, 1)
+/// variables.  It describes running sums like the A component of
+/// Adler-32 before the modulus wraps, once the sum is divided by the
+/// byte range.
+pub struct IrwinHallDistributionParameters {
+    /// The number of uniform variables summed
+    pub n: u32,
+}
+
+/// The CDF of the Irwin–Hall distribution
+///
+/// The exact formula is an alternating sum which loses precision as
+/// n grows, so for n > 20 the normal approximation with mean n / 2
+/// and variance n / 12 is used instead.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::distribution::{Cdf, IrwinHallDistributionParameters};
+///
+/// let parameters = IrwinHallDistributionParameters { n: 2 };
+/// // The sum of two uniforms is triangular on [0, 2]
+/// assert!(f32::abs(parameters.cdf(0.5) - 0.125) < 0.00001);
+/// assert!(f32::abs(parameters.cdf(1.0) - 0.5) < 0.00001);
+/// ```
+impl Cdf for IrwinHallDistributionParameters {
+    fn cdf(&self, x: f32) -> f32 {
+        let n = self.n as f64;
+        let x = x as f64;
+
+        if x <= 0.0 {
+            return 0.0;
+        }
+        if x >= n {
+            return 1.0;
+        }
+
+        if self.n > 20 {
+            return normal_cdf((x - n / 2.0) / f64::sqrt(n / 12.0)) as f32;
+        }
+
+        // F(x) = 1 / n! * sum_{k=0}^{floor(x)} (-1)^k C(n, k) (x - k)^n
+        // C(n, k) / n! = 1 / (k! (n - k)!)
+        let mut sum = 0.0;
+        for k in 0..=(x.floor() as u32) {
+            let k_f = k as f64;
+            let ln_coefficient = -ln_gamma(k_f + 1.0) - ln_gamma(n - k_f + 1.0);
+            let term = f64::exp(ln_coefficient + n * f64::ln(x - k_f));
+            if k % 2 == 0 {
+                sum += term;
+            } else {
+                sum -= term;
+            }
+        }
+
+        sum.clamp(0.0, 1.0) as f32
+    }
+}
+
+/// Counter mixed into time-based seeds, so distributions created in
+/// the same millisecond still get different seeds
+static SEED_COUNTER: AtomicU32 = AtomicU32::new(0);
+
 /// A discrete uniform distribution that takes on values from a to b
 /// inclusive: U[a; b]
 pub struct DiscreteUniformDistribution<'a> {
@@ -41,14 +240,51 @@ pub struct DiscreteUniformDistribution<'a> {
     pub parameters: DiscreteUniformDistributionParameters,
     /// The current state of the random number generator
     pub state: CRC<'a, u32>,
+    /// The seed the random number generator was initialized with
+    seed: u32,
+}
+
+impl<'a> DiscreteUniformDistribution<'a> {
+    /// Step the CRC and return the raw 32-bit state
+    fn next_u32(&mut self) -> u32 {
+        self.state.update((self.state.state() >> 24) as u8)
+    }
 }
 
 impl<'a> Distribution<u32> for DiscreteUniformDistribution<'a> {
+    /// Sample a value from U[a; b]
+    ///
+    /// Values are reduced into the range with rejection sampling.
+    /// Raw values at the top of the 32-bit space that would make some
+    /// results more likely than others are thrown away and a new
+    /// value is generated.
     fn sample(&mut self) -> Sample<u32> {
-        Sample {
-            sample: self.state.update((self.state.state() >> 24) as u8),
+        let a = self.parameters.a;
+        let b = self.parameters.b;
+
+        if a == 0 && b == u32::MAX {
+            return Sample {
+                sample: self.next_u32(),
+            };
+        }
+
+        let range: u64 = (b - a) as u64 + 1;
+        // The largest multiple of range that fits in 2^32 values
+        let zone: u64 = (1u64 << 32) - ((1u64 << 32) % range);
+
+        loop {
+            let value = self.next_u32() as u64;
+            if value < zone {
+                return Sample {
+                    sample: a + (value % range) as u32,
+                };
+            }
         }
     }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed.into())
+    }
 }
 
 impl<'a> DiscreteUniformDistribution<'a> {
@@ -61,10 +297,46 @@ impl<'a> DiscreteUniformDistribution<'a> {
     /// weakness is that CRCs don't cycle through zero, so the
     /// generated random distribution will be biased towards larger
     /// values without proper weighting.
+    ///
+    /// The seed is taken from the current time.  Use
+    /// [`DiscreteUniformDistribution::with_seed`] for reproducible
+    /// results.
     pub fn new(a: u32, b: u32) -> Self {
         let t = SystemTime::now();
         let t = t.duration_since(UNIX_EPOCH).unwrap().as_millis();
         let seed: u32 = (t % (u32::MAX as u128 + 1)) as u32;
+        // Spread the counter across the seed bits with the golden
+        // ratio constant so consecutive seeds aren't close together
+        let counter = SEED_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let seed = seed ^ counter.wrapping_mul(0x9E37_79B9);
+
+        Self::with_seed(a, b, seed)
+    }
+
+    /// Create a new DiscreteUniformDistribution with the given seed
+    ///
+    /// Two distributions created with the same seed generate the same
+    /// sequence of samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a is greater than b.
+    ///
+    /// # Examples
+    /// ```
+    /// use tapestry_analysis::analysis::distribution::{
+    ///     DiscreteUniformDistribution, Distribution,
+    /// };
+    ///
+    /// let mut first = DiscreteUniformDistribution::with_seed(0, u32::MAX, 42);
+    /// let mut second = DiscreteUniformDistribution::with_seed(0, u32::MAX, 42);
+    ///
+    /// assert_eq!(first.sample().sample, second.sample().sample);
+    /// assert_eq!(first.seed(), Some(42));
+    /// ```
+    pub fn with_seed(a: u32, b: u32, seed: u32) -> Self {
+        assert!(a <= b, "a must be less than or equal to b");
+
         let prng_crc = CRC::<u32>::new(
             CRCConfiguration::<u32>::new(
                 "CRC-32/ISO-HDLC",
@@ -81,6 +353,7 @@ impl<'a> DiscreteUniformDistribution<'a> {
         Self {
             parameters: DiscreteUniformDistributionParameters { a, b },
             state: prng_crc,
+            seed,
         }
     }
 }
@@ -97,7 +370,14 @@ pub fn normalize_variable(item: f32, parameters: &DiscreteUniformDistributionPar
 
 #[cfg(test)]
 mod tests {
-    use super::{normalize_variable, DiscreteUniformDistributionParameters};
+    use crate::analysis::chi_square::{test, uniform_probabilities};
+    use crate::analysis::histogram::SimpleHistogram;
+
+    use super::{
+        normalize_variable, Cdf, DiscreteUniformDistribution,
+        DiscreteUniformDistributionParameters, Distribution, IrwinHallDistributionParameters,
+        PoissonDistributionParameters,
+    };
 
     /// Test normalizing discrete uniform distrubution variables works
     #[test]
@@ -128,4 +408,113 @@ mod tests {
         assert_eq!(normalized_var_1, 0.75);
         assert_eq!(normalized_var_2, 0.00);
     }
+
+    /// The uniform CDF is clamped outside of [a, b]
+    #[test]
+    fn uniform_cdf_works() {
+        let parameters = DiscreteUniformDistributionParameters { a: 1, b: 3 };
+
+        assert_eq!(parameters.cdf(0.0), 0.0);
+        assert_eq!(parameters.cdf(2.5), 0.75);
+        assert_eq!(parameters.cdf(4.0), 1.0);
+        assert_eq!(parameters.cdf_left(2.5), 0.75);
+    }
+
+    /// Test the Poisson CDF against values computed with mpmath
+    #[test]
+    fn poisson_cdf_works() {
+        let parameters = PoissonDistributionParameters { lambda: 3.5 };
+
+        assert_eq!(parameters.cdf(-1.0), 0.0);
+        assert!(f32::abs(parameters.cdf(2.0) - 0.320_847) < 0.00001);
+        assert!(f32::abs(parameters.cdf(2.7) - 0.320_847) < 0.00001);
+        assert!(f32::abs(parameters.cdf_left(3.0) - 0.320_847) < 0.00001);
+        assert!(f32::abs(parameters.cdf(5.0) - 0.857_614) < 0.00001);
+    }
+
+    /// Test the Irwin–Hall CDF in the exact and approximated ranges
+    #[test]
+    fn irwin_hall_cdf_works() {
+        let parameters = IrwinHallDistributionParameters { n: 3 };
+        assert_eq!(parameters.cdf(-1.0), 0.0);
+        assert_eq!(parameters.cdf(3.0), 1.0);
+        assert!(f32::abs(parameters.cdf(1.5) - 0.5) < 0.00001);
+        // x^3 / 6 on [0, 1]
+        assert!(f32::abs(parameters.cdf(0.5) - 0.020_833) < 0.00001);
+
+        let parameters = IrwinHallDistributionParameters { n: 50 };
+        assert!(f32::abs(parameters.cdf(25.0) - 0.5) < 0.00001);
+    }
+
+    /// Distributions with the same seed generate the same samples
+    #[test]
+    fn with_seed_is_reproducible() {
+        let mut first = DiscreteUniformDistribution::with_seed(0, u32::MAX, 0x1234_5678);
+        let mut second = DiscreteUniformDistribution::with_seed(0, u32::MAX, 0x1234_5678);
+
+        for _ in 0..100 {
+            assert_eq!(first.sample().sample, second.sample().sample);
+        }
+        assert_eq!(first.seed(), Some(0x1234_5678));
+    }
+
+    /// Distributions created back to back get different seeds
+    #[test]
+    fn new_seeds_differ() {
+        let first = DiscreteUniformDistribution::new(0, u32::MAX);
+        let second = DiscreteUniformDistribution::new(0, u32::MAX);
+
+        assert_ne!(first.seed(), second.seed());
+    }
+
+    /// Samples stay inside [a, b] and reach both ends
+    #[test]
+    fn sample_honors_range() {
+        let mut dud = DiscreteUniformDistribution::with_seed(10, 19, 0xDEAD_BEEF);
+
+        let mut seen = [false; 10];
+        for _ in 0..1000 {
+            let sample = dud.sample().sample;
+            assert!((10..=19).contains(&sample));
+            seen[(sample - 10) as usize] = true;
+        }
+        assert!(seen.iter().all(|s| *s));
+
+        let mut single = DiscreteUniformDistribution::with_seed(7, 7, 1);
+        assert_eq!(single.sample().sample, 7);
+    }
+
+    /// Samples from a small range pass a chi-square uniformity test
+    #[test]
+    fn sample_is_uniform() {
+        let mut dud = DiscreteUniformDistribution::with_seed(100, 107, 0x0BAD_CAFE);
+
+        let mut bins: Vec<u32> = vec![0; 8];
+        for _ in 0..8000 {
+            bins[(dud.sample().sample - 100) as usize] += 1;
+        }
+        let histogram = SimpleHistogram::from_bins(bins, 32);
+
+        let result = test(&histogram, &uniform_probabilities(8)).unwrap();
+        assert!(result.p_value > 0.01);
+    }
+
+    /// A range that isn't a power of two still works near the top of
+    /// the 32-bit space
+    #[test]
+    fn sample_honors_large_range() {
+        let a = u32::MAX - 2_000_000_000;
+        let mut dud = DiscreteUniformDistribution::with_seed(a, u32::MAX, 99);
+
+        for _ in 0..1000 {
+            assert!(dud.sample().sample >= a);
+        }
+    }
+
+    /// An empty range is rejected
+    #[test]
+    #[should_panic]
+    fn with_seed_reversed_range_fails() {
+        DiscreteUniformDistribution::with_seed(5, 4, 1);
+    }
 }
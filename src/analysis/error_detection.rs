@@ -0,0 +1,614 @@
+//! Error-detection Monte Carlo simulator
+//! Estimate the fraction of corrupted messages a checksum fails to
+//! detect.
+//!
+//! Each trial takes a message from a [`MessageSource`], checksums it,
+//! sends it through a [`Channel`] that corrupts it, and checksums the
+//! received message.  A corrupted message with the same checksum as
+//! the original is an undetected error.  The checksum itself is
+//! assumed to arrive intact.
+//!
+//! The channel models cover the usual ways data gets damaged: random
+//! bit errors, bursts of errors, erased bytes, swapped bytes, and
+//! bytes inserted or dropped.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::{
+    distribution::{CriticalValue, Distribution},
+    experiment::OutputWidth,
+    message::MessageSource,
+    special::{bisect, normal_cdf},
+};
+use checksum_tapestry::{crc::BitOrder, Checksum};
+
+/// A model of a channel that corrupts messages
+pub trait Channel {
+    /// Send a message through the channel, replacing the contents of
+    /// received with what comes out the other end
+    ///
+    /// A channel may leave the message unchanged.  Those trials don't
+    /// count as errors.
+    fn transmit(&mut self, message: &[u8], received: &mut Vec<u8>);
+}
+
+/// Pick an index from 0 to n - 1 using a 32-bit sample
+fn uniform_index<D: Distribution<u32>>(prng: &mut D, n: usize) -> usize {
+    ((prng.sample().sample as u64 * n as u64) >> 32) as usize
+}
+
+/// Flip bit index of a message, with bits numbered in the order they
+/// are transmitted
+///
+/// With LSBFirst, bit 0 is the least significant bit of the first
+/// byte.  With MSBFirst, bit 0 is the most significant bit of the
+/// first byte.  A burst of errors is contiguous in transmission
+/// order, which must match the bit order of the CRC for the CRC's
+/// burst guarantees to hold.
+pub fn flip_bit(message: &mut [u8], index: usize, bit_order: BitOrder) {
+    let shift = match bit_order {
+        BitOrder::LSBFirst => index % 8,
+        BitOrder::MSBFirst => 7 - index % 8,
+    };
+
+    message[index / 8] ^= 1 << shift;
+}
+
+/// A binary symmetric channel, each bit is flipped independently with
+/// the same probability
+pub struct BinarySymmetricChannel<D: Distribution<u32>> {
+    /// The distribution used to pick errors
+    pub prng: D,
+    /// The probability of each bit being flipped
+    pub bit_error_rate: f64,
+}
+
+impl<D: Distribution<u32>> BinarySymmetricChannel<D> {
+    /// Create a new binary symmetric channel
+    ///
+    /// # Panics
+    ///
+    /// Panics if bit_error_rate is not between zero and one.
+    pub fn new(prng: D, bit_error_rate: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&bit_error_rate),
+            "bit_error_rate must be between 0 and 1"
+        );
+
+        Self {
+            prng,
+            bit_error_rate,
+        }
+    }
+}
+
+impl<D: Distribution<u32>> Channel for BinarySymmetricChannel<D> {
+    fn transmit(&mut self, message: &[u8], received: &mut Vec<u8>) {
+        // Compare against the threshold in 32-bit fixed point
+        let threshold = (self.bit_error_rate * (1u64 << 32) as f64) as u64;
+
+        received.clear();
+        received.extend_from_slice(message);
+        for i in 0..received.len() * 8 {
+            if (self.prng.sample().sample as u64) < threshold {
+                flip_bit(received, i, BitOrder::LSBFirst);
+            }
+        }
+    }
+}
+
+/// A channel that corrupts a burst of bits in each message
+///
+/// A burst of length L starts and ends with a flipped bit, and the
+/// bits between are random.  The burst is placed at a random offset,
+/// contiguous in the given transmission bit order.  Messages shorter
+/// than the burst are sent unchanged.
+pub struct BurstChannel<D: Distribution<u32>> {
+    /// The distribution used to place and fill bursts
+    pub prng: D,
+    /// The length of each burst in bits
+    pub length: usize,
+    /// The order bits are transmitted in
+    pub bit_order: BitOrder,
+}
+
+impl<D: Distribution<u32>> BurstChannel<D> {
+    /// Create a new burst error channel
+    ///
+    /// # Panics
+    ///
+    /// Panics if length is zero.
+    pub fn new(prng: D, length: usize, bit_order: BitOrder) -> Self {
+        assert!(length > 0, "length must be at least one bit");
+
+        Self {
+            prng,
+            length,
+            bit_order,
+        }
+    }
+}
+
+impl<D: Distribution<u32>> Channel for BurstChannel<D> {
+    fn transmit(&mut self, message: &[u8], received: &mut Vec<u8>) {
+        received.clear();
+        received.extend_from_slice(message);
+
+        let bits = received.len() * 8;
+        if bits < self.length {
+            return;
+        }
+
+        let start = uniform_index(&mut self.prng, bits - self.length + 1);
+        let end = start + self.length - 1;
+        flip_bit(received, start, self.bit_order);
+        for i in (start + 1)..end {
+            if self.prng.sample().sample >> 31 == 1 {
+                flip_bit(received, i, self.bit_order);
+            }
+        }
+        if end != start {
+            flip_bit(received, end, self.bit_order);
+        }
+    }
+}
+
+/// A channel that erases bytes, replacing them with zero
+///
+/// This models dropped out fields that read back as zero.  Erasing a
+/// byte that was already zero doesn't change the message.
+pub struct ByteErasureChannel<D: Distribution<u32>> {
+    /// The distribution used to pick the erased bytes
+    pub prng: D,
+    /// The number of bytes erased in each message
+    pub erasures: usize,
+}
+
+impl<D: Distribution<u32>> ByteErasureChannel<D> {
+    /// Create a new byte erasure channel
+    pub fn new(prng: D, erasures: usize) -> Self {
+        Self { prng, erasures }
+    }
+}
+
+impl<D: Distribution<u32>> Channel for ByteErasureChannel<D> {
+    fn transmit(&mut self, message: &[u8], received: &mut Vec<u8>) {
+        received.clear();
+        received.extend_from_slice(message);
+        if received.is_empty() {
+            return;
+        }
+
+        for _ in 0..self.erasures {
+            let index = uniform_index(&mut self.prng, received.len());
+            received[index] = 0;
+        }
+    }
+}
+
+/// A channel that swaps two bytes in each message
+///
+/// Swapping two equal bytes doesn't change the message.
+pub struct ByteSwapChannel<D: Distribution<u32>> {
+    /// The distribution used to pick the swapped bytes
+    pub prng: D,
+    /// Only swap neighbouring bytes, a transposition
+    pub adjacent: bool,
+}
+
+impl<D: Distribution<u32>> ByteSwapChannel<D> {
+    /// Create a new byte swap channel
+    pub fn new(prng: D, adjacent: bool) -> Self {
+        Self { prng, adjacent }
+    }
+}
+
+impl<D: Distribution<u32>> Channel for ByteSwapChannel<D> {
+    fn transmit(&mut self, message: &[u8], received: &mut Vec<u8>) {
+        received.clear();
+        received.extend_from_slice(message);
+
+        let n = received.len();
+        if n < 2 {
+            return;
+        }
+
+        if self.adjacent {
+            let i = uniform_index(&mut self.prng, n - 1);
+            received.swap(i, i + 1);
+        } else {
+            let i = uniform_index(&mut self.prng, n);
+            // Pick from the other n - 1 positions
+            let j = (i + 1 + uniform_index(&mut self.prng, n - 1)) % n;
+            received.swap(i, j);
+        }
+    }
+}
+
+/// A channel that inserts or deletes a single byte in each message
+///
+/// Each message has an equal chance of a random byte being inserted
+/// at a random position, or a byte at a random position being
+/// deleted.  This models framing and synchronization errors.
+pub struct InsertionDeletionChannel<D: Distribution<u32>> {
+    /// The distribution used to pick the change
+    pub prng: D,
+}
+
+impl<D: Distribution<u32>> InsertionDeletionChannel<D> {
+    /// Create a new insertion and deletion channel
+    pub fn new(prng: D) -> Self {
+        Self { prng }
+    }
+}
+
+impl<D: Distribution<u32>> Channel for InsertionDeletionChannel<D> {
+    fn transmit(&mut self, message: &[u8], received: &mut Vec<u8>) {
+        received.clear();
+        received.extend_from_slice(message);
+
+        let insert = self.prng.sample().sample >> 31 == 1;
+        if insert || received.is_empty() {
+            let index = uniform_index(&mut self.prng, received.len() + 1);
+            let byte = (self.prng.sample().sample >> 24) as u8;
+            received.insert(index, byte);
+        } else {
+            let index = uniform_index(&mut self.prng, received.len());
+            received.remove(index);
+        }
+    }
+}
+
+/// The result of an error-detection simulation
+#[derive(Debug)]
+pub struct ErrorDetectionTest {
+    /// The number of messages sent
+    pub trials: u64,
+    /// The number of messages the channel changed
+    pub errors: u64,
+    /// The number of changed messages with the same checksum as the
+    /// original
+    pub undetected: u64,
+    /// The fraction of changed messages that weren't detected
+    pub undetected_rate: f64,
+    /// The lower and upper bounds of the confidence interval for the
+    /// undetected rate
+    pub confidence_interval: (f64, f64),
+}
+
+/// The Wilson score interval for a binomial proportion
+///
+/// Unlike the normal approximation, this stays inside zero and one
+/// and gives a useful upper bound when no events were seen, which is
+/// the usual case for a good checksum.
+///
+/// Wilson, E. B. (1927). Probable Inference, the Law of Succession,
+/// and Statistical Inference. Journal of the American Statistical
+/// Association, 22(158), 209–212.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{distribution::CriticalValue, error_detection::wilson_interval};
+///
+/// let (low, high) = wilson_interval(0, 1000, CriticalValue::FivePercent);
+/// assert_eq!(low, 0.0);
+/// assert!(f64::abs(high - 0.003_827) < 1.0e-6);
+/// ```
+pub fn wilson_interval(successes: u64, trials: u64, cv: CriticalValue) -> (f64, f64) {
+    if trials == 0 {
+        return (0.0, 1.0);
+    }
+
+    // The two-sided normal quantile for the confidence level
+    let target = 1.0 - cv.alpha() as f64 / 2.0;
+    let z = bisect(normal_cdf, target, 0.0, 10.0);
+
+    let n = trials as f64;
+    let p = successes as f64 / n;
+    let z2 = z * z;
+    let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let half_width = z / (1.0 + z2 / n) * f64::sqrt(p * (1.0 - p) / n + z2 / (4.0 * n * n));
+
+    (
+        (center - half_width).max(0.0),
+        (center + half_width).min(1.0),
+    )
+}
+
+/// Simulate sending messages through a channel and count the errors
+/// the checksum misses
+///
+/// The confidence interval is two-sided at the level of the critical
+/// value, so FivePercent gives a 95% interval.  Returns None if the
+/// channel never changed a message.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{
+///     distribution::CriticalValue,
+///     error_detection::{simulate, BinarySymmetricChannel},
+///     message::UniformMessages,
+///     prng::Pcg32,
+/// };
+/// use checksum_tapestry::crc::CRC;
+///
+/// let mut source = UniformMessages::new(Pcg32::new(1), 64);
+/// let mut channel = BinarySymmetricChannel::new(Pcg32::new(2), 0.001);
+/// let mut crc32 = CRC::<u32>::default();
+///
+/// let result = simulate(&mut source, &mut channel, &mut crc32, 1000, CriticalValue::FivePercent)
+///     .unwrap();
+/// assert_eq!(result.undetected, 0);
+/// ```
+pub fn simulate<T: OutputWidth>(
+    source: &mut dyn MessageSource,
+    channel: &mut dyn Channel,
+    checksum: &mut dyn Checksum<T>,
+    num_trials: u32,
+    cv: CriticalValue,
+) -> Option<ErrorDetectionTest> {
+    let mut message: Vec<u8> = Vec::new();
+    let mut received: Vec<u8> = Vec::new();
+    let mut errors: u64 = 0;
+    let mut undetected: u64 = 0;
+
+    for _ in 0..num_trials {
+        message.resize(source.next_length(), 0);
+        source.fill(&mut message);
+        channel.transmit(&message, &mut received);
+
+        if received == message {
+            continue;
+        }
+        errors += 1;
+
+        let sent = checksum.compute(&message).to_u64();
+        checksum.reset();
+        let check = checksum.compute(&received).to_u64();
+        checksum.reset();
+
+        if sent == check {
+            undetected += 1;
+        }
+    }
+
+    if errors == 0 {
+        return None;
+    }
+
+    Some(ErrorDetectionTest {
+        trials: num_trials.into(),
+        errors,
+        undetected,
+        undetected_rate: undetected as f64 / errors as f64,
+        confidence_interval: wilson_interval(undetected, errors, cv),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        distribution::CriticalValue,
+        error_detection::{
+            flip_bit, simulate, wilson_interval, BinarySymmetricChannel, BurstChannel,
+            ByteErasureChannel, ByteSwapChannel, Channel, InsertionDeletionChannel,
+        },
+        message::UniformMessages,
+        prng::Pcg32,
+    };
+    use checksum_tapestry::{
+        adler32::Adler32,
+        crc::{BitOrder, CRC},
+        fletcher16::Fletcher16,
+        Checksum,
+    };
+
+    /// A sum of the bytes modulo 256, which can't see the order of
+    /// the bytes
+    struct Sum8 {
+        sum: u8,
+    }
+
+    impl Checksum<u8> for Sum8 {
+        fn compute(&mut self, data: &[u8]) -> u8 {
+            for byte in data {
+                self.update(*byte);
+            }
+
+            self.sum
+        }
+
+        fn update(&mut self, data: u8) -> u8 {
+            self.sum = self.sum.wrapping_add(data);
+
+            self.sum
+        }
+
+        fn reset(&mut self) {
+            self.sum = 0;
+        }
+    }
+
+    /// Test against Wilson intervals computed with mpmath
+    #[test]
+    fn wilson_interval_works() {
+        let (low, high) = wilson_interval(10, 100, CriticalValue::FivePercent);
+        assert!(f64::abs(low - 0.055_229) < 1.0e-6);
+        assert!(f64::abs(high - 0.174_366) < 1.0e-6);
+
+        let (low, high) = wilson_interval(100, 100, CriticalValue::OnePercent);
+        assert!(f64::abs(low - 0.937_779) < 1.0e-6);
+        assert_eq!(high, 1.0);
+
+        assert_eq!(
+            wilson_interval(0, 0, CriticalValue::FivePercent),
+            (0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn flip_bit_works() {
+        let mut message = [0u8; 2];
+        flip_bit(&mut message, 0, BitOrder::LSBFirst);
+        flip_bit(&mut message, 9, BitOrder::LSBFirst);
+        assert_eq!(message, [0x01, 0x02]);
+
+        let mut message = [0u8; 2];
+        flip_bit(&mut message, 0, BitOrder::MSBFirst);
+        flip_bit(&mut message, 9, BitOrder::MSBFirst);
+        assert_eq!(message, [0x80, 0x40]);
+    }
+
+    /// Bursts are the right length and inside the message
+    #[test]
+    fn burst_channel_works() {
+        let mut channel = BurstChannel::new(Pcg32::new(1), 12, BitOrder::LSBFirst);
+        let message = [0u8; 8];
+        let mut received = Vec::new();
+
+        for _ in 0..100 {
+            channel.transmit(&message, &mut received);
+            let bits = u64::from_le_bytes(received.clone().try_into().unwrap());
+            let span = 64 - bits.leading_zeros() - bits.trailing_zeros();
+            assert_eq!(span, 12);
+        }
+
+        // Messages shorter than the burst aren't changed
+        channel.transmit(&[0xAB], &mut received);
+        assert_eq!(received, [0xAB]);
+    }
+
+    /// Each channel changes the message in the expected way
+    #[test]
+    fn channels_work() {
+        let message: Vec<u8> = (1..=16).collect();
+        let mut received = Vec::new();
+
+        let mut erasure = ByteErasureChannel::new(Pcg32::new(2), 2);
+        erasure.transmit(&message, &mut received);
+        let erased = received.iter().filter(|b| **b == 0).count();
+        assert!((1..=2).contains(&erased));
+
+        let mut swap = ByteSwapChannel::new(Pcg32::new(3), true);
+        swap.transmit(&message, &mut received);
+        let changed: Vec<usize> = (0..16).filter(|i| received[*i] != message[*i]).collect();
+        assert_eq!(changed.len(), 2);
+        assert_eq!(changed[1], changed[0] + 1);
+
+        let mut swap = ByteSwapChannel::new(Pcg32::new(3), false);
+        for _ in 0..100 {
+            swap.transmit(&message, &mut received);
+            assert_ne!(received, message);
+        }
+
+        let mut indel = InsertionDeletionChannel::new(Pcg32::new(4));
+        let mut lengths = [false; 2];
+        for _ in 0..100 {
+            indel.transmit(&message, &mut received);
+            match received.len() {
+                15 => lengths[0] = true,
+                17 => lengths[1] = true,
+                _ => unreachable!(),
+            }
+        }
+        assert_eq!(lengths, [true, true]);
+
+        let mut bsc = BinarySymmetricChannel::new(Pcg32::new(5), 0.0);
+        bsc.transmit(&message, &mut received);
+        assert_eq!(received, message);
+        let mut bsc = BinarySymmetricChannel::new(Pcg32::new(5), 1.0);
+        bsc.transmit(&message, &mut received);
+        assert!(received.iter().zip(message.iter()).all(|(r, m)| *r == !*m));
+    }
+
+    /// CRC-32 detects every burst up to 32 bits long
+    #[test]
+    fn simulate_works_crc32_burst() {
+        let mut source = UniformMessages::new(Pcg32::new(6), 32);
+        let mut channel = BurstChannel::new(Pcg32::new(7), 32, BitOrder::LSBFirst);
+        let mut crc32 = CRC::<u32>::default();
+
+        let result = simulate(
+            &mut source,
+            &mut channel,
+            &mut crc32,
+            2000,
+            CriticalValue::FivePercent,
+        )
+        .unwrap();
+        assert_eq!(result.errors, 2000);
+        assert_eq!(result.undetected, 0);
+        assert!(result.confidence_interval.1 < 0.002);
+    }
+
+    /// A plain sum misses every byte swap
+    #[test]
+    fn simulate_works_sum_swap() {
+        let mut source = UniformMessages::new(Pcg32::new(8), 16);
+        let mut channel = ByteSwapChannel::new(Pcg32::new(9), false);
+        let mut sum8 = Sum8 { sum: 0 };
+
+        let result = simulate(
+            &mut source,
+            &mut channel,
+            &mut sum8,
+            1000,
+            CriticalValue::FivePercent,
+        )
+        .unwrap();
+        assert_eq!(result.undetected, result.errors);
+        assert_eq!(result.undetected_rate, 1.0);
+    }
+
+    /// Fletcher-16 can't tell 0x00 from 0xFF, so erasing a 0xFF byte
+    /// is missed.  Adler-32's larger modulus catches it.
+    #[test]
+    fn simulate_works_erasure() {
+        let mut source = UniformMessages::new(Pcg32::new(10), 4);
+        let mut channel = ByteErasureChannel::new(Pcg32::new(11), 1);
+        let mut fletcher16 = Fletcher16::default();
+
+        let result = simulate(
+            &mut source,
+            &mut channel,
+            &mut fletcher16,
+            20_000,
+            CriticalValue::FivePercent,
+        )
+        .unwrap();
+        // About one erased byte in 255 was 0xFF
+        assert!(result.undetected > 0);
+        assert!(result.confidence_interval.0 < 1.0 / 255.0);
+        assert!(result.confidence_interval.1 > 1.0 / 255.0);
+
+        let mut adler32 = Adler32::default();
+        let result = simulate(
+            &mut source,
+            &mut channel,
+            &mut adler32,
+            20_000,
+            CriticalValue::FivePercent,
+        )
+        .unwrap();
+        assert_eq!(result.undetected, 0);
+    }
+
+    /// Channels that never change anything can't be simulated
+    #[test]
+    fn simulate_no_errors_fails() {
+        let mut source = UniformMessages::new(Pcg32::new(12), 16);
+        let mut channel = BinarySymmetricChannel::new(Pcg32::new(13), 0.0);
+        let mut crc32 = CRC::<u32>::default();
+
+        assert!(simulate(
+            &mut source,
+            &mut channel,
+            &mut crc32,
+            10,
+            CriticalValue::FivePercent
+        )
+        .is_none());
+    }
+}
@@ -1,47 +1,442 @@
 //! Statistical experiment structures and implementations
 //!
-use crate::analysis::{distribution::Distribution, sample::Sample};
+use crate::analysis::{
+    distribution::{Distribution, UniformOutputDistributionParameters},
+    message::{ExhaustiveMessages, MessageSource, UniformMessages},
+    sample::Sample,
+};
 use checksum_tapestry::Checksum;
 
+/// The longest message length [`Experiment::run_exhaustive`] keeps a
+/// sample of every message for, 2^24 samples
+///
+/// Use [`coverage::test_exhaustive`] to count the outputs of longer
+/// messages without keeping the samples.
+///
+/// [`coverage::test_exhaustive`]: crate::analysis::coverage::test_exhaustive
+pub const MAX_EXHAUSTIVE_LENGTH: usize = 3;
+
+/// The output type of a checksum
+///
+/// This is implemented for the unsigned integer types checksums
+/// return, so experiments, histograms and tests can derive the size
+/// of the output space from the type instead of assuming 32 bits.
+pub trait OutputWidth: Copy {
+    /// The number of bits in the output
+    const BITS: u32;
+
+    /// Widen the output to a u64
+    fn to_u64(self) -> u64;
+
+    /// The largest possible output, 2^BITS - 1
+    fn max_output() -> u64 {
+        u64::MAX >> (64 - Self::BITS)
+    }
+}
+
+impl OutputWidth for u8 {
+    const BITS: u32 = u8::BITS;
+
+    fn to_u64(self) -> u64 {
+        self.into()
+    }
+}
+
+impl OutputWidth for u16 {
+    const BITS: u32 = u16::BITS;
+
+    fn to_u64(self) -> u64 {
+        self.into()
+    }
+}
+
+impl OutputWidth for u32 {
+    const BITS: u32 = u32::BITS;
+
+    fn to_u64(self) -> u64 {
+        self.into()
+    }
+}
+
+impl OutputWidth for u64 {
+    const BITS: u32 = u64::BITS;
+
+    fn to_u64(self) -> u64 {
+        self
+    }
+}
+
 /// A single experiment
 /// An experiment is a collection of samples, usually from the same
 /// distribution.
 pub struct Experiment<T> {
     /// Samples in this experiment
     pub samples: Vec<Sample<T>>,
+    /// The seed of the distribution the messages were generated from,
+    /// if it has one.  Create the distribution again with this seed
+    /// to replay the experiment.
+    pub seed: Option<u64>,
+    /// The seed of the distribution the message lengths were drawn
+    /// from, if the lengths were random and it has one
+    pub length_seed: Option<u64>,
+}
+
+impl<T> Experiment<T> {
+    /// Create an experiment from samples that weren't generated from a
+    /// seeded distribution
+    ///
+    /// # Examples
+    /// ```
+    /// use tapestry_analysis::analysis::{experiment::Experiment, sample::Sample};
+    ///
+    /// let samples = vec![Sample { sample: 1u32 }, Sample { sample: 2 }];
+    /// let experiment = Experiment::from_samples(samples);
+    /// assert_eq!(experiment.samples.len(), 2);
+    /// assert_eq!(experiment.seed, None);
+    /// ```
+    pub fn from_samples(samples: Vec<Sample<T>>) -> Experiment<T> {
+        Experiment {
+            samples,
+            seed: None,
+            length_seed: None,
+        }
+    }
 }
 
 /// Run an experiment for a given checksum algorithm.
-/// Generates a set of random byte strings, and then calculates the checksum for that data.
+/// Generates a set of byte strings, and then calculates the checksum for that data.
 /// Repeats this several times and returns the data.
-impl<T> Experiment<T> {
+impl<T: OutputWidth> Experiment<T> {
     /// Run an experiment
+    ///
+    /// The output type of the experiment is the output type of the
+    /// checksum, so 8, 16, 32 and 64-bit checksums can all be run.
+    ///
+    /// # Examples
+    /// ```
+    /// use tapestry_analysis::analysis::{
+    ///     distribution::DiscreteUniformDistribution, experiment::Experiment,
+    /// };
+    /// use checksum_tapestry::fletcher16::Fletcher16;
+    ///
+    /// let mut dud = DiscreteUniformDistribution::new(0, u32::MAX);
+    /// let mut fletcher16 = Fletcher16::default();
+    /// let experiment: Experiment<u16> = Experiment::run(&mut dud, &mut fletcher16, 50, 100);
+    /// assert_eq!(experiment.samples.len(), 100);
+    /// ```
     pub fn run(
         prng: &mut dyn Distribution<u32>,
-        checksum: &mut dyn Checksum<u32>,
+        checksum: &mut dyn Checksum<T>,
         message_size: u32,
         num_experiments: u32,
-    ) -> Experiment<u32> {
-        let mut random_buffer: Vec<u8> = vec![0; message_size.try_into().unwrap()];
-        let mut experiments: Vec<u32> = vec![0; num_experiments.try_into().unwrap()];
+    ) -> Experiment<T> {
+        let mut source = UniformMessages::new(prng, message_size.try_into().unwrap());
+
+        Self::run_source(&mut source, checksum, num_experiments)
+    }
+
+    /// Run an experiment with messages from a message source
+    ///
+    /// # Examples
+    /// ```
+    /// use tapestry_analysis::analysis::{
+    ///     distribution::DiscreteUniformDistribution,
+    ///     experiment::Experiment,
+    ///     message::SparseMessages,
+    /// };
+    /// use checksum_tapestry::adler32::Adler32;
+    ///
+    /// // Messages where one byte in twenty is non-zero
+    /// let dud = DiscreteUniformDistribution::new(0, u32::MAX);
+    /// let mut source = SparseMessages::new(dud, 50, 0.05);
+    /// let mut adler32 = Adler32::default();
+    /// let experiment = Experiment::<u32>::run_source(&mut source, &mut adler32, 100);
+    /// assert_eq!(experiment.samples.len(), 100);
+    /// ```
+    pub fn run_source(
+        source: &mut dyn MessageSource,
+        checksum: &mut dyn Checksum<T>,
+        num_experiments: u32,
+    ) -> Experiment<T> {
+        let seed = source.seed();
+        let length_seed = source.length_seed();
+        let mut message: Vec<u8> = Vec::new();
 
         // Run the experiment
-        for i in 0..num_experiments {
-            // Generate a random message string
-            let mut last: u32;
-            for item in &mut random_buffer {
-                last = prng.sample().sample;
-                *item = (last >> 24) as u8;
-            }
-
-            let result = checksum.compute(&random_buffer);
-            checksum.reset();
-
-            experiments[i as usize] = result;
+        let samples = (0..num_experiments)
+            .map(|_| {
+                // Generate the next message
+                message.resize(source.next_length(), 0);
+                source.fill(&mut message);
+
+                let sample = checksum.compute(&message);
+                checksum.reset();
+
+                Sample { sample }
+            })
+            .collect();
+
+        Experiment {
+            samples,
+            seed,
+            length_seed,
         }
+    }
+
+    /// Run an experiment over every message of the given length
+    ///
+    /// The samples are the full multiset of checksum outputs, so
+    /// coverage and collision counts from it are exact.  There is one
+    /// sample for each of the 256^length messages.
+    ///
+    /// # Examples
+    /// ```
+    /// use tapestry_analysis::analysis::experiment::Experiment;
+    /// use checksum_tapestry::adler32::Adler32;
+    ///
+    /// let mut adler32 = Adler32::default();
+    /// let experiment = Experiment::<u32>::run_exhaustive(&mut adler32, 2);
+    /// assert_eq!(experiment.samples.len(), 65536);
+    /// assert_eq!(experiment.seed, None);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if length is greater than [`MAX_EXHAUSTIVE_LENGTH`].
+    pub fn run_exhaustive(checksum: &mut dyn Checksum<T>, length: usize) -> Experiment<T> {
+        assert!(
+            length <= MAX_EXHAUSTIVE_LENGTH,
+            "length must be <= {MAX_EXHAUSTIVE_LENGTH}"
+        );
+
+        Self::run_enumeration(&mut ExhaustiveMessages::new(length), checksum)
+    }
+
+    /// Run an experiment over every non-empty message up to
+    /// max_length bytes long
+    ///
+    /// # Panics
+    ///
+    /// Panics if max_length is zero or greater than
+    /// [`MAX_EXHAUSTIVE_LENGTH`].
+    pub fn run_exhaustive_up_to(
+        checksum: &mut dyn Checksum<T>,
+        max_length: usize,
+    ) -> Experiment<T> {
+        assert!(
+            max_length <= MAX_EXHAUSTIVE_LENGTH,
+            "max_length must be <= {MAX_EXHAUSTIVE_LENGTH}"
+        );
 
+        Self::run_enumeration(&mut ExhaustiveMessages::up_to(max_length), checksum)
+    }
+
+    /// Checksum every message from an exhaustive source once
+    fn run_enumeration(
+        source: &mut ExhaustiveMessages,
+        checksum: &mut dyn Checksum<T>,
+    ) -> Experiment<T> {
+        let mut message: Vec<u8> = Vec::new();
+
+        let samples = (0..source.num_messages())
+            .map(|_| {
+                message.resize(source.next_length(), 0);
+                source.fill(&mut message);
+
+                let sample = checksum.compute(&message);
+                checksum.reset();
+
+                Sample { sample }
+            })
+            .collect();
+
+        Experiment::from_samples(samples)
+    }
+
+    /// The uniform distribution over every possible output of the
+    /// checksum, the expected distribution for a good checksum
+    pub fn uniform_parameters(&self) -> UniformOutputDistributionParameters {
+        UniformOutputDistributionParameters { bits: T::BITS }
+    }
+
+    /// Convert the samples to f32 for the goodness of fit tests
+    ///
+    /// Outputs wider than 24 bits are rounded to the nearest f32.
+    pub fn to_f32(&self) -> Experiment<f32> {
         Experiment {
-            samples: experiments.iter().map(|d| Sample { sample: *d }).collect(),
+            samples: self
+                .samples
+                .iter()
+                .map(|s| Sample {
+                    sample: s.sample.to_u64() as f32,
+                })
+                .collect(),
+            seed: self.seed,
+            length_seed: self.length_seed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::analysis::{
+        distribution::{Cdf, DiscreteUniformDistribution},
+        experiment::{Experiment, OutputWidth},
+        ks::{cdf_statistic, p_value},
+        message::{SparseMessages, UniformMessages, VariableLengthMessages},
+        prng::Pcg32,
+        test_support::Mixed,
+    };
+    use checksum_tapestry::{
+        adler32::Adler32,
+        crc::{BitOrder, BitWidth, CRCConfiguration, CRC},
+        fletcher16::Fletcher16,
+    };
+
+    /// An experiment can be replayed from its recorded seed
+    #[test]
+    fn run_replays_from_seed() {
+        let mut dud = DiscreteUniformDistribution::new(0, u32::MAX);
+        let mut adler32 = Adler32::default();
+        let first = Experiment::<u32>::run(&mut dud, &mut adler32, 50, 100);
+
+        let seed: u32 = first.seed.unwrap().try_into().unwrap();
+        let mut dud = DiscreteUniformDistribution::with_seed(0, u32::MAX, seed);
+        let second = Experiment::<u32>::run(&mut dud, &mut adler32, 50, 100);
+
+        assert_eq!(second.seed, first.seed);
+        for (a, b) in first.samples.iter().zip(second.samples.iter()) {
+            assert_eq!(a.sample, b.sample);
+        }
+    }
+
+    /// An experiment with random lengths can be replayed from both
+    /// recorded seeds
+    #[test]
+    fn run_source_replays_variable_length() {
+        let run = |seed: u64, length_seed: u32| {
+            let bytes = UniformMessages::new(Pcg32::new(seed), 0);
+            let lengths = DiscreteUniformDistribution::with_seed(1, 64, length_seed);
+            let mut source = VariableLengthMessages::new(bytes, lengths);
+            let mut adler32 = Adler32::default();
+            Experiment::<u32>::run_source(&mut source, &mut adler32, 100)
+        };
+
+        let first = run(21, 22);
+        assert_eq!(first.seed, Some(21));
+        assert_eq!(first.length_seed, Some(22));
+
+        let second = run(
+            first.seed.unwrap(),
+            first.length_seed.unwrap().try_into().unwrap(),
+        );
+        for (a, b) in first.samples.iter().zip(second.samples.iter()) {
+            assert_eq!(a.sample, b.sample);
         }
     }
+
+    /// The output space is derived from the output type
+    #[test]
+    fn output_width_works() {
+        assert_eq!(u8::max_output(), 0xFF);
+        assert_eq!(u16::max_output(), 0xFFFF);
+        assert_eq!(u32::max_output(), 0xFFFF_FFFF);
+        assert_eq!(u64::max_output(), u64::MAX);
+    }
+
+    /// 16-bit checksums run through the same KS pipeline as 32-bit ones
+    #[test]
+    fn run_works_16_bit() {
+        let mut dud = DiscreteUniformDistribution::with_seed(0, u32::MAX, 11);
+        let mut crc16 = CRC::<u16>::new(
+            CRCConfiguration::<u16>::new(
+                "CRC-16/ARC",
+                BitWidth::Sixteen,
+                BitOrder::LSBFirst,
+                0x8005,
+                true,
+                None,
+                None,
+            ),
+            true,
+        );
+        let experiment: Experiment<u16> = Experiment::run(&mut dud, &mut crc16, 50, 1000);
+        let parameters = experiment.uniform_parameters();
+        assert_eq!(parameters.bits, 16);
+        assert_eq!(parameters.cdf(65535.0), 1.0);
+
+        let statistic = cdf_statistic(experiment.to_f32(), &parameters);
+        assert!(p_value(statistic, 1000) > 0.001);
+
+        // Fletcher-16 sums modulo 255, so neither byte is ever 0xFF
+        let mut fletcher16 = Fletcher16::default();
+        let experiment: Experiment<u16> = Experiment::run(&mut dud, &mut fletcher16, 50, 1000);
+        assert!(experiment
+            .samples
+            .iter()
+            .all(|s| s.sample & 0xFF != 0xFF && s.sample >> 8 != 0xFF));
+    }
+
+    /// 64-bit outputs are tested against the full 64-bit output space
+    #[test]
+    fn run_works_64_bit() {
+        let mut dud = DiscreteUniformDistribution::with_seed(0, u32::MAX, 12);
+        let mut mixed = Mixed::default();
+        let experiment: Experiment<u64> = Experiment::run(&mut dud, &mut mixed, 50, 1000);
+
+        let statistic = cdf_statistic(experiment.to_f32(), &experiment.uniform_parameters());
+        assert!(p_value(statistic, 1000) > 0.001);
+    }
+
+    /// Sparse messages expose Adler-32's small sums
+    #[test]
+    fn run_source_works_sparse() {
+        let dud = DiscreteUniformDistribution::with_seed(0, u32::MAX, 13);
+        let mut source = SparseMessages::new(dud, 50, 0.05);
+        let mut adler32 = Adler32::default();
+        let experiment = Experiment::<u32>::run_source(&mut source, &mut adler32, 1000);
+
+        assert_eq!(experiment.seed, Some(13));
+        let statistic = cdf_statistic(experiment.to_f32(), &experiment.uniform_parameters());
+        assert!(p_value(statistic, 1000) < 0.001);
+    }
+
+    /// Exact output counts for Adler-32 and Fletcher-16 on tiny
+    /// messages
+    #[test]
+    fn run_exhaustive_works() {
+        let distinct = |samples: Vec<u64>| samples.iter().collect::<BTreeSet<_>>().len();
+
+        // Adler-32 doesn't wrap on two bytes, so every output is unique
+        let mut adler32 = Adler32::default();
+        let experiment = Experiment::<u32>::run_exhaustive(&mut adler32, 2);
+        assert_eq!(experiment.samples.len(), 65536);
+        assert_eq!(
+            distinct(experiment.samples.iter().map(|s| s.sample as u64).collect()),
+            65536
+        );
+
+        // Fletcher-16 sums modulo 255, so 0x00 and 0xFF are the same
+        // byte and only 255^2 outputs are reachable
+        let mut fletcher16 = Fletcher16::default();
+        let experiment = Experiment::<u16>::run_exhaustive(&mut fletcher16, 2);
+        assert_eq!(
+            distinct(experiment.samples.iter().map(|s| s.sample as u64).collect()),
+            255 * 255
+        );
+
+        let experiment = Experiment::<u16>::run_exhaustive_up_to(&mut fletcher16, 2);
+        assert_eq!(experiment.samples.len(), 256 + 65536);
+        assert_eq!(experiment.samples[0].sample, 0);
+    }
+
+    /// Four byte messages are too many to keep a sample for each
+    #[test]
+    #[should_panic]
+    fn run_exhaustive_too_long_fails() {
+        let mut adler32 = Adler32::default();
+        Experiment::<u32>::run_exhaustive(&mut adler32, 4);
+    }
 }
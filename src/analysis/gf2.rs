@@ -0,0 +1,635 @@
+//! Polynomial arithmetic over GF(2)
+//! Add, multiply and divide polynomials with coefficients of zero or
+//! one, and test the properties of CRC generator polynomials.
+//!
+//! A polynomial is stored as the bits of an integer, with bit i the
+//! coefficient of x^i, so addition and subtraction are both XOR.
+//! The generator polynomial of a CRC includes the x^width term that
+//! [`CRCConfiguration`] leaves out of its poly field.
+//!
+//! The order of a polynomial f(x) with a +1 term is the smallest e
+//! where f(x) divides x^e + 1, also called its period or exponent.  A
+//! CRC with a generator of order e misses two bit errors e bits apart.
+//! A primitive polynomial of degree n is irreducible with the largest
+//! possible order, 2^n - 1.
+//!
+//! Lidl, R., & Niederreiter, H. (1997). Finite Fields (2nd ed.).
+//! Cambridge University Press.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use std::{
+    fmt::{Display, Formatter, Result},
+    ops::{Add, Div, Mul, Rem},
+};
+
+use checksum_tapestry::crc::{CRCConfiguration, Width};
+
+/// A polynomial over GF(2) of degree up to 127
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Polynomial(pub u128);
+
+impl Polynomial {
+    /// The zero polynomial
+    pub const ZERO: Polynomial = Polynomial(0);
+    /// The polynomial 1
+    pub const ONE: Polynomial = Polynomial(1);
+    /// The polynomial x
+    pub const X: Polynomial = Polynomial(2);
+
+    /// The generator polynomial of a CRC configuration, including the
+    /// x^width term
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tapestry_analysis::analysis::gf2::Polynomial;
+    /// use checksum_tapestry::crc::{BitOrder, BitWidth, CRCConfiguration};
+    ///
+    /// let configuration = CRCConfiguration::<u16>::new(
+    ///     "CRC-16/ARC",
+    ///     BitWidth::Sixteen,
+    ///     BitOrder::LSBFirst,
+    ///     0x8005,
+    ///     true,
+    ///     None,
+    ///     None,
+    /// );
+    /// let generator = Polynomial::from_configuration(&configuration);
+    ///
+    /// assert_eq!(generator, Polynomial(0x1_8005));
+    /// assert_eq!(generator.to_string(), "x^16 + x^15 + x^2 + 1");
+    /// ```
+    pub fn from_configuration<T: Width + Into<u64>>(
+        configuration: &CRCConfiguration<T>,
+    ) -> Polynomial {
+        let width = configuration.width as u32;
+        let poly: u64 = configuration.poly.into();
+
+        Polynomial((1 << width) | poly as u128)
+    }
+
+    /// The monomial x^n
+    ///
+    /// # Panics
+    ///
+    /// Panics if n is greater than 127.
+    pub fn monomial(n: u32) -> Polynomial {
+        assert!(n < 128, "the degree must be less than 128");
+
+        Polynomial(1 << n)
+    }
+
+    /// The degree of the polynomial, or None for the zero polynomial
+    pub fn degree(self) -> Option<u32> {
+        self.0.checked_ilog2()
+    }
+
+    /// Returns true if this is the zero polynomial
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The number of non-zero coefficients
+    pub fn weight(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Divide by another polynomial, giving the quotient and remainder
+    ///
+    /// # Panics
+    ///
+    /// Panics if the divisor is zero.
+    pub fn div_rem(self, divisor: Polynomial) -> (Polynomial, Polynomial) {
+        let divisor_degree = divisor.degree().expect("division by the zero polynomial");
+        let mut quotient: u128 = 0;
+        let mut remainder = self.0;
+
+        while let Some(degree) = remainder.checked_ilog2() {
+            if degree < divisor_degree {
+                break;
+            }
+            let shift = degree - divisor_degree;
+            quotient |= 1 << shift;
+            remainder ^= divisor.0 << shift;
+        }
+
+        (Polynomial(quotient), Polynomial(remainder))
+    }
+
+    /// Returns true if this polynomial divides other with no remainder
+    pub fn divides(self, other: Polynomial) -> bool {
+        (other % self).is_zero()
+    }
+
+    /// Multiply two polynomials modulo another
+    ///
+    /// The operands don't need to be reduced first.
+    pub fn mul_mod(self, other: Polynomial, modulus: Polynomial) -> Polynomial {
+        let a = self % modulus;
+        let b = other % modulus;
+        let mut product = Polynomial::ZERO;
+
+        // Shift and add, reducing as we go so nothing overflows
+        for i in (0..=b.degree().unwrap_or(0)).rev() {
+            product = (product * Polynomial::X) % modulus;
+            if (b.0 >> i) & 1 == 1 {
+                product = product + a;
+            }
+        }
+
+        product
+    }
+
+    /// Raise this polynomial to a power modulo another
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tapestry_analysis::analysis::gf2::Polynomial;
+    ///
+    /// // x^7 = 1 modulo the primitive polynomial x^3 + x + 1
+    /// let modulus = Polynomial(0b1011);
+    /// assert_eq!(Polynomial::X.pow_mod(7, modulus), Polynomial::ONE);
+    /// assert_ne!(Polynomial::X.pow_mod(3, modulus), Polynomial::ONE);
+    /// ```
+    pub fn pow_mod(self, exponent: u128, modulus: Polynomial) -> Polynomial {
+        let mut result = Polynomial::ONE % modulus;
+        let mut base = self % modulus;
+        let mut exponent = exponent;
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.mul_mod(base, modulus);
+            }
+            base = base.mul_mod(base, modulus);
+            exponent >>= 1;
+        }
+
+        result
+    }
+
+    /// x^(2^k) modulo this polynomial, by squaring x k times
+    fn x_pow_pow2(self, k: u32) -> Polynomial {
+        (0..k).fold(Polynomial::X % self, |p, _| p.mul_mod(p, self))
+    }
+
+    /// Returns true if the polynomial can't be written as the product
+    /// of two polynomials of lower degree
+    ///
+    /// This uses Rabin's test: f(x) of degree n is irreducible if it
+    /// divides x^(2^n) - x and shares no factor with x^(2^(n/q)) - x
+    /// for each prime q dividing n.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tapestry_analysis::analysis::gf2::Polynomial;
+    ///
+    /// assert!(Polynomial(0b1011).is_irreducible());
+    /// // x^2 + 1 = (x + 1)^2
+    /// assert!(!Polynomial(0b101).is_irreducible());
+    /// ```
+    pub fn is_irreducible(self) -> bool {
+        let n = match self.degree() {
+            None | Some(0) => return false,
+            Some(n) => n,
+        };
+
+        if self.x_pow_pow2(n) != Polynomial::X % self {
+            return false;
+        }
+
+        prime_factors(n as u128).iter().all(|q| {
+            let p = self.x_pow_pow2(n / *q as u32) + Polynomial::X;
+            gcd(p, self) == Polynomial::ONE
+        })
+    }
+
+    /// The irreducible factors of the polynomial and their
+    /// multiplicities, lowest degree first
+    ///
+    /// Factors are found by trial division, which is quick for CRC
+    /// generators up to degree 32 or so.  The zero polynomial and one
+    /// have no factors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tapestry_analysis::analysis::gf2::Polynomial;
+    ///
+    /// // CRC-16/ARC is (x + 1)(x^15 + x + 1)
+    /// let factors = Polynomial(0x1_8005).factor();
+    /// assert_eq!(factors, vec![(Polynomial(0b11), 1), (Polynomial(0x8003), 1)]);
+    /// ```
+    pub fn factor(self) -> Vec<(Polynomial, u32)> {
+        let mut factors: Vec<(Polynomial, u32)> = Vec::new();
+        let mut remaining = self;
+        if remaining.degree().unwrap_or(0) == 0 {
+            return factors;
+        }
+
+        let mut divide_out = |remaining: &mut Polynomial, factor: Polynomial| {
+            let mut multiplicity = 0;
+            loop {
+                let (quotient, remainder) = remaining.div_rem(factor);
+                if !remainder.is_zero() {
+                    break;
+                }
+                *remaining = quotient;
+                multiplicity += 1;
+            }
+            if multiplicity > 0 {
+                factors.push((factor, multiplicity));
+            }
+        };
+
+        divide_out(&mut remaining, Polynomial::X);
+
+        // Only try candidates with a +1 term.  Every composite candidate
+        // has a factor of lower degree that was already divided out.
+        let mut degree = 1;
+        while 2 * degree <= remaining.degree().unwrap_or(0) {
+            for middle in 0..(1u128 << (degree - 1)) {
+                let candidate = Polynomial((1 << degree) | (middle << 1) | 1);
+                divide_out(&mut remaining, candidate);
+            }
+            degree += 1;
+        }
+
+        if remaining.degree().unwrap_or(0) > 0 {
+            factors.push((remaining, 1));
+        }
+        factors.sort_by_key(|(f, _)| (f.degree(), f.0));
+
+        factors
+    }
+
+    /// The multiplicative order of x modulo this polynomial, the
+    /// smallest e where it divides x^e + 1
+    ///
+    /// Returns None if the polynomial has no +1 term, since then it
+    /// never divides x^e + 1, or if its degree is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tapestry_analysis::analysis::gf2::Polynomial;
+    ///
+    /// // CRC-16/CCITT's generator has a period of 32767
+    /// assert_eq!(Polynomial(0x1_1021).order(), Some(32767));
+    /// ```
+    pub fn order(self) -> Option<u128> {
+        if self.0 & 1 == 0 || self.degree().unwrap_or(0) == 0 {
+            return None;
+        }
+
+        // The order is the least common multiple of the orders of the
+        // distinct irreducible factors, times the smallest power of two
+        // at least as large as the highest multiplicity
+        let factors = self.factor();
+        let mut order: u128 = 1;
+        for (factor, _) in &factors {
+            let factor_order = factor.irreducible_order();
+            order = order / gcd_u128(order, factor_order) * factor_order;
+        }
+        let max_multiplicity = factors.iter().map(|(_, m)| *m).max().unwrap_or(1);
+
+        Some(order * (max_multiplicity as u128).next_power_of_two())
+    }
+
+    /// The order of x modulo an irreducible polynomial with a +1 term,
+    /// which divides 2^n - 1
+    fn irreducible_order(self) -> u128 {
+        let n = self.degree().unwrap_or(0);
+        let mut order = (1u128 << n) - 1;
+
+        for q in prime_factors(order) {
+            while order % q == 0
+                && Polynomial::X.pow_mod(order / q, self) == Polynomial::ONE
+            {
+                order /= q;
+            }
+        }
+
+        order
+    }
+
+    /// Returns true if the polynomial is irreducible with order 2^n - 1
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tapestry_analysis::analysis::gf2::Polynomial;
+    ///
+    /// assert!(Polynomial(0b1011).is_primitive());
+    /// // x^4 + x^3 + x^2 + x + 1 is irreducible but has order 5
+    /// assert!(!Polynomial(0b11111).is_primitive());
+    /// ```
+    pub fn is_primitive(self) -> bool {
+        match self.degree() {
+            Some(n) if self.0 & 1 == 1 && n > 0 && self.is_irreducible() => {
+                self.irreducible_order() == (1u128 << n) - 1
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The greatest common divisor of two polynomials
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::gf2::{gcd, Polynomial};
+///
+/// // (x + 1)(x^2 + x + 1) and (x + 1)^2
+/// assert_eq!(gcd(Polynomial(0b1001), Polynomial(0b101)), Polynomial(0b11));
+/// ```
+pub fn gcd(a: Polynomial, b: Polynomial) -> Polynomial {
+    let (mut a, mut b) = (a, b);
+    while !b.is_zero() {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+/// The greatest common divisor of two integers
+fn gcd_u128(a: u128, b: u128) -> u128 {
+    let (mut a, mut b) = (a, b);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+/// a * b mod m without overflowing
+fn mul_mod_u128(a: u128, b: u128, m: u128) -> u128 {
+    let (mut a, mut b) = (a % m, b % m);
+    let mut result = 0;
+
+    while b > 0 {
+        if b & 1 == 1 {
+            result = (result + a) % m;
+        }
+        a = (a << 1) % m;
+        b >>= 1;
+    }
+
+    result
+}
+
+/// Returns true if n is prime, using the Miller-Rabin test with bases
+/// that are exact for every n below 2^64
+fn is_prime(n: u128) -> bool {
+    const BASES: [u128; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    if let Some(p) = BASES.iter().find(|p| n % **p == 0) {
+        return n == *p;
+    }
+
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    BASES.iter().all(|a| {
+        let mut x = 1;
+        let (mut base, mut e) = (*a, d);
+        while e > 0 {
+            if e & 1 == 1 {
+                x = mul_mod_u128(x, base, n);
+            }
+            base = mul_mod_u128(base, base, n);
+            e >>= 1;
+        }
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        (1..s).any(|_| {
+            x = mul_mod_u128(x, x, n);
+            x == n - 1
+        })
+    })
+}
+
+/// The distinct prime factors of n, smallest first
+///
+/// This uses trial division, stopping once the rest is prime, which is
+/// quick for 2^n - 1 with n up to 64.
+fn prime_factors(n: u128) -> Vec<u128> {
+    let mut factors: Vec<u128> = Vec::new();
+    let mut n = n;
+    let mut p = 2;
+    let mut rest_is_prime = is_prime(n);
+
+    while n > 1 && !rest_is_prime && p * p <= n {
+        if n % p == 0 {
+            factors.push(p);
+            while n % p == 0 {
+                n /= p;
+            }
+            rest_is_prime = is_prime(n);
+        }
+        p += if p == 2 { 1 } else { 2 };
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+
+    factors
+}
+
+impl Add for Polynomial {
+    type Output = Polynomial;
+
+    // Addition over GF(2) is XOR
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, other: Polynomial) -> Polynomial {
+        Polynomial(self.0 ^ other.0)
+    }
+}
+
+impl Mul for Polynomial {
+    type Output = Polynomial;
+
+    /// Multiply two polynomials
+    ///
+    /// # Panics
+    ///
+    /// Panics if the product has a degree greater than 127.
+    fn mul(self, other: Polynomial) -> Polynomial {
+        let (a, b) = match (self.degree(), other.degree()) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return Polynomial::ZERO,
+        };
+        assert!(a + b < 128, "the product's degree must be less than 128");
+
+        let mut product: u128 = 0;
+        for i in 0..=b {
+            if (other.0 >> i) & 1 == 1 {
+                product ^= self.0 << i;
+            }
+        }
+
+        Polynomial(product)
+    }
+}
+
+impl Div for Polynomial {
+    type Output = Polynomial;
+
+    fn div(self, divisor: Polynomial) -> Polynomial {
+        self.div_rem(divisor).0
+    }
+}
+
+impl Rem for Polynomial {
+    type Output = Polynomial;
+
+    fn rem(self, divisor: Polynomial) -> Polynomial {
+        self.div_rem(divisor).1
+    }
+}
+
+/// Format a polynomial with the highest power first, like
+/// x^3 + x + 1
+impl Display for Polynomial {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let Some(degree) = self.degree() else {
+            return write!(f, "0");
+        };
+
+        let terms: Vec<String> = (0..=degree)
+            .rev()
+            .filter(|i| (self.0 >> i) & 1 == 1)
+            .map(|i| match i {
+                0 => String::from("1"),
+                1 => String::from("x"),
+                _ => format!("x^{i}"),
+            })
+            .collect();
+
+        write!(f, "{}", terms.join(" + "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::gf2::{gcd, is_prime, prime_factors, Polynomial};
+
+    #[test]
+    fn arithmetic_works() {
+        let a = Polynomial(0b1011);
+        let b = Polynomial(0b111);
+
+        assert_eq!(a + a, Polynomial::ZERO);
+        // (x^3 + x + 1)(x^2 + x + 1) = x^5 + x^4 + 1
+        assert_eq!(a * b, Polynomial(0b110001));
+        assert_eq!(a * Polynomial::ZERO, Polynomial::ZERO);
+        assert_eq!((a * b) / b, a);
+        assert_eq!((a * b + Polynomial::X) % b, Polynomial::X);
+        assert_eq!(a.div_rem(b), (Polynomial(0b11), Polynomial(0b10)));
+        assert!(b.divides(a * b));
+        assert_eq!(a.mul_mod(b, Polynomial(0b10011)), Polynomial(0b100));
+        assert_eq!(gcd(a * b, b * b), b);
+        assert_eq!(gcd(a, b), Polynomial::ONE);
+    }
+
+    #[test]
+    #[should_panic]
+    fn divide_by_zero_panics() {
+        let _ = Polynomial::ONE / Polynomial::ZERO;
+    }
+
+    #[test]
+    fn display_works() {
+        assert_eq!(Polynomial::ZERO.to_string(), "0");
+        assert_eq!(Polynomial::ONE.to_string(), "1");
+        assert_eq!(Polynomial(0b1011).to_string(), "x^3 + x + 1");
+        assert_eq!(Polynomial::monomial(100).degree(), Some(100));
+    }
+
+    #[test]
+    fn prime_factors_works() {
+        assert!(is_prime(2));
+        assert!(is_prime((1 << 61) - 1));
+        assert!(!is_prime(1));
+        assert!(!is_prime(3 * 5 * 17 * 257));
+        assert_eq!(prime_factors((1 << 32) - 1), vec![3, 5, 17, 257, 65537]);
+        assert_eq!(prime_factors((1 << 61) - 1), vec![(1 << 61) - 1]);
+        assert_eq!(prime_factors(1), vec![]);
+    }
+
+    /// Count the irreducible polynomials of each small degree
+    #[test]
+    fn is_irreducible_works() {
+        let expected = [0, 2, 1, 2, 3, 6, 9, 18, 30];
+        for (degree, count) in expected.iter().enumerate().skip(1) {
+            let found = (1u128 << degree..1u128 << (degree + 1))
+                .filter(|p| Polynomial(*p).is_irreducible())
+                .count();
+            assert_eq!(found, *count);
+        }
+        assert!(!Polynomial::ZERO.is_irreducible());
+        assert!(!Polynomial::ONE.is_irreducible());
+    }
+
+    /// Factors multiply back to the polynomial and are irreducible
+    #[test]
+    fn factor_works() {
+        for p in [
+            0x1_04C1_1DB7u128,
+            0x1_1EDC_6F41,
+            0x1_8005,
+            0x1_1021,
+            0x1_8004,
+            0x100,
+        ] {
+            let p = Polynomial(p);
+            let factors = p.factor();
+            let product = factors.iter().fold(Polynomial::ONE, |product, (f, m)| {
+                (0..*m).fold(product, |product, _| product * *f)
+            });
+            assert_eq!(product, p);
+            assert!(factors.iter().all(|(f, _)| f.is_irreducible()));
+        }
+
+        // x^8 and (x + 1)^2 (x^2 + x + 1)
+        assert_eq!(Polynomial(0x100).factor(), vec![(Polynomial::X, 8)]);
+        let p = Polynomial(0b11) * Polynomial(0b11) * Polynomial(0b111);
+        assert_eq!(
+            p.factor(),
+            vec![(Polynomial(0b11), 2), (Polynomial(0b111), 1)]
+        );
+        assert!(Polynomial::ONE.factor().is_empty());
+    }
+
+    #[test]
+    fn order_works() {
+        // CRC-32 is primitive
+        let crc32 = Polynomial(0x1_04C1_1DB7);
+        assert!(crc32.is_irreducible());
+        assert!(crc32.is_primitive());
+        assert_eq!(crc32.order(), Some((1 << 32) - 1));
+
+        // CRC-16/ARC is (x + 1) times a primitive polynomial of
+        // degree 15
+        let arc = Polynomial(0x1_8005);
+        assert!(!arc.is_irreducible());
+        assert_eq!(arc.order(), Some(32767));
+
+        // (x + 1)^2 has order 2, x^4 + x^3 + x^2 + x + 1 has order 5
+        assert_eq!(Polynomial(0b101).order(), Some(2));
+        assert_eq!(Polynomial(0b11111).order(), Some(5));
+        assert_eq!(Polynomial(0x1_8004).order(), None);
+
+        // The order agrees with searching for x^e = 1
+        for p in (0b1001u128..0b1_0000_0000).step_by(2) {
+            let p = Polynomial(p);
+            let e = (1..).find(|e| Polynomial::X.pow_mod(*e, p) == Polynomial::ONE);
+            assert_eq!(p.order(), e);
+        }
+    }
+}
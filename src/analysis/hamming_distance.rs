@@ -0,0 +1,747 @@
+//! Minimum Hamming distance of CRC polynomials
+//! Find the smallest number of bit errors a CRC can miss for a given
+//! data word length, and how many error patterns of each small weight
+//! it misses.
+//!
+//! A codeword is the data word followed by the CRC, and an error
+//! pattern is missed when the CRC of the corrupted data word matches
+//! the corrupted CRC.  CRCs are linear, so this only depends on the
+//! error pattern: flipping codeword bit t changes the remainder by
+//! x^t mod G(x), the syndrome of that bit, and a pattern is missed
+//! when the syndromes of its bits XOR to zero.  The initial value,
+//! final XOR and bit order don't change which patterns are missed.
+//!
+//! Instead of checking messages, the missed patterns are counted by
+//! their span.  Shifting a missed pattern gives another missed
+//! pattern, so it's enough to count the ones starting at bit zero for
+//! each span and multiply by the number of places they fit.
+//!
+//! Koopman, P. (2002). 32-Bit Cyclic Redundancy Codes for Internet
+//! Applications. International Conference on Dependable Systems and
+//! Networks.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter, Result},
+};
+
+use crate::analysis::gf2::Polynomial;
+use checksum_tapestry::crc::{CRCConfiguration, Width};
+
+/// The largest error pattern weight counted
+pub const MAX_WEIGHT: usize = 6;
+
+/// The Hamming distance of a CRC at a data word length
+#[derive(Debug)]
+pub struct HammingDistance {
+    /// The length of the data word in bits
+    pub data_length: usize,
+    /// The length of the codeword in bits, the data word and the CRC
+    pub codeword_length: usize,
+    /// The smallest number of bit errors that can go undetected, or
+    /// None if it's more than the largest weight counted
+    pub hamming_distance: Option<u32>,
+    /// The number of undetected error patterns of each weight, indexed
+    /// by weight from 0 to the largest weight counted
+    ///
+    /// Weights zero and one are always zero.
+    pub weights: Vec<u64>,
+}
+
+/// A range of data word lengths with the same Hamming distance
+#[derive(Debug, PartialEq)]
+pub struct ProfileRow {
+    /// The Hamming distance, or None if it's more than the largest
+    /// weight counted
+    pub hamming_distance: Option<u32>,
+    /// The shortest data word length in bits
+    pub min_data_length: usize,
+    /// The longest data word length in bits
+    pub max_data_length: usize,
+}
+
+/// The Hamming distance of a CRC over a range of data word lengths
+#[derive(Debug)]
+pub struct HammingDistanceProfile {
+    /// The ranges of data word lengths, shortest first, where each
+    /// range has a lower Hamming distance than the one before it
+    pub rows: Vec<ProfileRow>,
+    /// The largest error pattern weight counted
+    pub max_weight: usize,
+}
+
+/// Format a Hamming distance, showing None as more than max_weight
+fn format_distance(hamming_distance: Option<u32>, max_weight: usize) -> String {
+    match hamming_distance {
+        Some(hd) => hd.to_string(),
+        None => format!(">{max_weight}"),
+    }
+}
+
+/// Format a profile as a table
+impl Display for HammingDistanceProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f, "{:>4}  {:>10}  {:>10}", "HD", "From bits", "To bits")?;
+        for row in &self.rows {
+            writeln!(
+                f,
+                "{:>4}  {:>10}  {:>10}",
+                format_distance(row.hamming_distance, self.max_weight),
+                row.min_data_length,
+                row.max_data_length
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl HammingDistanceProfile {
+    /// Format the profile as CSV with a header line
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tapestry_analysis::analysis::hamming_distance::{profile, MAX_WEIGHT};
+    /// use checksum_tapestry::crc::{BitOrder, BitWidth, CRCConfiguration};
+    ///
+    /// let configuration = CRCConfiguration::<u16>::new(
+    ///     "CRC-8/SMBUS",
+    ///     BitWidth::Eight,
+    ///     BitOrder::MSBFirst,
+    ///     0x07,
+    ///     false,
+    ///     None,
+    ///     None,
+    /// );
+    /// let csv = profile(&configuration, 256, MAX_WEIGHT).unwrap().to_csv();
+    /// assert!(csv.starts_with("hamming_distance,min_data_length,max_data_length\n"));
+    /// assert!(csv.ends_with(",256\n"));
+    /// ```
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("hamming_distance,min_data_length,max_data_length\n");
+        for row in &self.rows {
+            csv.push_str(&format!(
+                "{},{},{}\n",
+                format_distance(row.hamming_distance, self.max_weight),
+                row.min_data_length,
+                row.max_data_length
+            ));
+        }
+
+        csv
+    }
+}
+
+/// The bits of a generator polynomial, including the x^width term,
+/// and its width
+///
+/// Returns None if the polynomial doesn't have a +1 term.  Every
+/// standard CRC has one, and without it shifted error patterns aren't
+/// missed in the same way.  Also returns None for degrees outside 1 to
+/// 63.
+fn generator(generator: Polynomial) -> Option<(u64, u32)> {
+    let width = generator.degree()?;
+    if generator.0 & 1 == 0 || !(1..64).contains(&width) {
+        return None;
+    }
+
+    Some((generator.0 as u64, width))
+}
+
+/// Counts the missed error patterns that start at bit zero, one span
+/// at a time
+///
+/// The pattern's first bit is bit zero and its last bit is bit span,
+/// so the other bits are "middle" bits from 1 to span - 1.
+struct ZeroSets {
+    generator: u64,
+    width: u32,
+    /// The syndrome of each bit up to the current span
+    syndromes: Vec<u64>,
+    /// The number of middle bits with each syndrome
+    counts: HashMap<u64, u64>,
+    /// The number of pairs of middle bits whose syndromes XOR to each
+    /// value, only kept while weights five and six are counted
+    ///
+    /// This has an entry for every distinct value, up to span² / 2 of
+    /// them or 2^width if that's fewer.
+    pairs: HashMap<u64, u64>,
+    max_weight: usize,
+}
+
+impl ZeroSets {
+    /// Start counting for a generator polynomial with the x^width
+    /// term, for weights up to max_weight
+    fn new(generator: u64, width: u32, max_weight: usize) -> ZeroSets {
+        ZeroSets {
+            generator,
+            width,
+            syndromes: vec![1],
+            counts: HashMap::new(),
+            pairs: HashMap::new(),
+            max_weight: max_weight.min(MAX_WEIGHT),
+        }
+    }
+
+    /// Stop counting weights above max_weight
+    fn limit(&mut self, max_weight: usize) {
+        self.max_weight = self.max_weight.min(max_weight);
+        if self.max_weight < 5 {
+            self.pairs = HashMap::new();
+        }
+    }
+
+    /// Count the missed patterns with the next span, indexed by weight
+    ///
+    /// The first call counts span one, covering two bits.  Counting
+    /// weight six takes time proportional to the square of the span.
+    fn next(&mut self) -> [u64; MAX_WEIGHT + 1] {
+        let span = self.syndromes.len();
+        let last = self.syndromes[span - 1] << 1;
+        let syndrome = if (last >> self.width) & 1 == 1 {
+            last ^ self.generator
+        } else {
+            last
+        };
+
+        // The middle bits have to XOR to the syndromes of the first
+        // and last bits
+        let target = 1 ^ syndrome;
+        let middle = &self.syndromes[1..];
+        let m = middle.len() as u64;
+        let count = |value: u64| self.counts.get(&value).copied().unwrap_or(0);
+        let pair_count = |value: u64| self.pairs.get(&value).copied().unwrap_or(0);
+
+        let mut zero_sets = [0; MAX_WEIGHT + 1];
+        zero_sets[2] = (target == 0) as u64;
+        if self.max_weight >= 3 {
+            zero_sets[3] = count(target);
+        }
+        if self.max_weight >= 4 {
+            let ordered: u64 = middle.iter().map(|s| count(s ^ target)).sum();
+            let same = if target == 0 { m } else { 0 };
+            zero_sets[4] = (ordered - same) / 2;
+        }
+        if self.max_weight >= 5 {
+            // Remove the pairs that include the first bit of the triple
+            let ordered: u64 = middle
+                .iter()
+                .map(|s| pair_count(s ^ target) + (*s == target) as u64 - count(target))
+                .sum();
+            zero_sets[5] = ordered / 3;
+        }
+        if self.max_weight >= 6 {
+            // Pairs of different pairs, less the ones that share a bit,
+            // count each set of four three times
+            let mut ordered: u64 = self
+                .pairs
+                .iter()
+                .map(|(value, n)| n * pair_count(value ^ target))
+                .sum();
+            if target == 0 {
+                ordered -= self.pairs.values().sum::<u64>();
+            }
+            let sharing = pair_count(target) * m.saturating_sub(2);
+            zero_sets[6] = (ordered / 2 - sharing) / 3;
+        }
+
+        // The last bit becomes a middle bit for the longer spans
+        if self.max_weight >= 5 {
+            for s in middle {
+                *self.pairs.entry(s ^ syndrome).or_insert(0) += 1;
+            }
+        }
+        *self.counts.entry(syndrome).or_insert(0) += 1;
+        self.syndromes.push(syndrome);
+
+        zero_sets
+    }
+}
+
+/// Compute the minimum Hamming distance of a CRC configuration and
+/// the number of undetected error patterns of weights two to
+/// max_weight, for data words of data_length bits
+///
+/// max_weight is capped at [`MAX_WEIGHT`].  Only the polynomial and
+/// width of the configuration are used.  Returns None if the data
+/// length is zero, max_weight is less than two or the polynomial
+/// doesn't have a +1 term.
+///
+/// Up to weight four, the time taken grows with the square of the
+/// codeword length and the memory used grows linearly.  Counting
+/// weights five and six keeps a table with an entry for each distinct
+/// XOR of two bit syndromes, up to half the square of the codeword
+/// length, or 2^width entries if that's fewer, at around 32 bytes
+/// each.  Weight six also takes time that grows with the cube of the
+/// codeword length.  A 32-bit CRC over 4000 bits needs about 250 MB,
+/// and over a 1500 byte data word about 2 GB, so pass a max_weight of
+/// four for long data words.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::hamming_distance::{compute, MAX_WEIGHT};
+/// use checksum_tapestry::crc::{BitOrder, BitWidth, CRCConfiguration};
+///
+/// let configuration = CRCConfiguration::<u16>::new(
+///     "CRC-16/ARC",
+///     BitWidth::Sixteen,
+///     BitOrder::LSBFirst,
+///     0x8005,
+///     true,
+///     None,
+///     None,
+/// );
+/// let result = compute(&configuration, 64, MAX_WEIGHT).unwrap();
+///
+/// // x + 1 divides the polynomial, so every odd number of bit
+/// // errors is detected
+/// assert_eq!(result.hamming_distance, Some(4));
+/// assert_eq!(result.weights[3], 0);
+/// assert_eq!(result.weights[5], 0);
+/// ```
+pub fn compute<T: Width + Into<u64>>(
+    configuration: &CRCConfiguration<T>,
+    data_length: usize,
+    max_weight: usize,
+) -> Option<HammingDistance> {
+    compute_generator(
+        Polynomial::from_configuration(configuration),
+        data_length,
+        max_weight,
+    )
+}
+
+/// Compute the minimum Hamming distance of a generator polynomial,
+/// including its x^width term, like [`compute`]
+///
+/// This also works for widths that [`CRCConfiguration`] doesn't
+/// support.  Returns None if the data length is zero, max_weight is
+/// less than two, the polynomial doesn't have a +1 term or its degree
+/// is more than 63.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{
+///     gf2::Polynomial,
+///     hamming_distance::{compute_generator, MAX_WEIGHT},
+/// };
+///
+/// // CRC-24/OPENPGP
+/// let result = compute_generator(Polynomial(0x186_4CFB), 64, MAX_WEIGHT).unwrap();
+/// assert_eq!(result.codeword_length, 88);
+/// assert_eq!(result.hamming_distance, Some(6));
+///
+/// // Only up to weight four, which is quicker and uses less memory
+/// let result = compute_generator(Polynomial(0x186_4CFB), 64, 4).unwrap();
+/// assert_eq!(result.hamming_distance, None);
+/// assert_eq!(result.weights, [0; 5]);
+/// ```
+pub fn compute_generator(
+    generator: Polynomial,
+    data_length: usize,
+    max_weight: usize,
+) -> Option<HammingDistance> {
+    if max_weight < 2 {
+        return None;
+    }
+
+    let weights = count_weights(generator, data_length, max_weight)?;
+    let codeword_length = data_length + generator.degree()? as usize;
+
+    let hamming_distance = weights
+        .iter()
+        .position(|w| *w > 0)
+        .map(|weight| weight as u32);
+
+    Some(HammingDistance {
+        data_length,
+        codeword_length,
+        hamming_distance,
+        weights,
+    })
+}
+
+/// The number of undetected error patterns of each weight up to
+/// max_weight, for a generator polynomial and data words of
+/// data_length bits
+///
+/// Returns None in the same cases as [`compute_generator`].
+pub(crate) fn count_weights(
+    generator_polynomial: Polynomial,
+    data_length: usize,
+    max_weight: usize,
+) -> Option<Vec<u64>> {
+    let (generator, width) = generator(generator_polynomial)?;
+    if data_length == 0 {
+        return None;
+    }
+
+    let max_weight = max_weight.min(MAX_WEIGHT);
+    let codeword_length = data_length + width as usize;
+    let mut zero_sets = ZeroSets::new(generator, width, max_weight);
+    let mut weights: Vec<u64> = vec![0; max_weight + 1];
+
+    for span in 1..codeword_length {
+        let placements = (codeword_length - span) as u64;
+        for (weight, count) in zero_sets.next().iter().take(max_weight + 1).enumerate() {
+            weights[weight] += count * placements;
+        }
+    }
+
+    Some(weights)
+}
+
+/// Returns true if a generator polynomial detects every error pattern
+/// of fewer than weight bits in data words of data_length bits
+///
+/// This stops at the first undetected pattern, so it's much quicker
+/// than counting them when the answer is no.
+pub(crate) fn detects_all_below(
+    generator_polynomial: Polynomial,
+    data_length: usize,
+    weight: usize,
+) -> bool {
+    let Some((generator, width)) = generator(generator_polynomial) else {
+        return false;
+    };
+
+    let codeword_length = data_length + width as usize;
+    let mut zero_sets = ZeroSets::new(generator, width, weight.saturating_sub(1));
+    (1..codeword_length).all(|_| {
+        zero_sets.next()[..weight.min(MAX_WEIGHT + 1)]
+            .iter()
+            .all(|c| *c == 0)
+    })
+}
+
+/// Find the Hamming distance of a CRC configuration for every data
+/// word length from one to max_data_length bits, counting error
+/// patterns of up to max_weight bits
+///
+/// The profile lists the lengths where the Hamming distance drops, in
+/// the same form as Koopman's tables.  Once a weight is missed only
+/// the lower weights are counted, so long data words are quick to
+/// check once the Hamming distance is four or less.  Until then the
+/// time and memory used are the same as [`compute`] at the length
+/// where the Hamming distance drops to four, or max_data_length if it
+/// doesn't.
+///
+/// max_weight is capped at [`MAX_WEIGHT`].  Only the polynomial and
+/// width of the configuration are used.  Returns None if
+/// max_data_length is zero, max_weight is less than two or the
+/// polynomial doesn't have a +1 term.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::hamming_distance::{profile, ProfileRow, MAX_WEIGHT};
+/// use checksum_tapestry::crc::{BitOrder, BitWidth, CRCConfiguration};
+///
+/// let configuration = CRCConfiguration::<u16>::new(
+///     "CRC-16/ARC",
+///     BitWidth::Sixteen,
+///     BitOrder::LSBFirst,
+///     0x8005,
+///     true,
+///     None,
+///     None,
+/// );
+/// let result = profile(&configuration, 40000, MAX_WEIGHT).unwrap();
+/// println!("{result}");
+///
+/// // HD=4 up to 32751 bits, then only two bit errors can be missed
+/// let last = result.rows.last().unwrap();
+/// assert_eq!(last.hamming_distance, Some(2));
+/// assert_eq!(last.min_data_length, 32752);
+/// ```
+pub fn profile<T: Width + Into<u64>>(
+    configuration: &CRCConfiguration<T>,
+    max_data_length: usize,
+    max_weight: usize,
+) -> Option<HammingDistanceProfile> {
+    let (generator, width) = generator(Polynomial::from_configuration(configuration))?;
+    if max_data_length == 0 || max_weight < 2 {
+        return None;
+    }
+
+    let max_weight = max_weight.min(MAX_WEIGHT);
+    let width = width as usize;
+    let mut zero_sets = ZeroSets::new(generator, width as u32, max_weight);
+    let mut rows: Vec<ProfileRow> = Vec::new();
+    let mut hamming_distance: Option<u32> = None;
+    let mut min_data_length = 1;
+
+    // A pattern with span s first fits in a codeword of s + 1 bits, a
+    // data word of s + 1 - width bits
+    for span in 1..(max_data_length + width) {
+        let counts = zero_sets.next();
+        let Some(weight) = (2..=max_weight).find(|w| counts[*w] > 0) else {
+            continue;
+        };
+
+        let data_length = (span + 1).saturating_sub(width).max(1);
+        if data_length > min_data_length {
+            rows.push(ProfileRow {
+                hamming_distance,
+                min_data_length,
+                max_data_length: data_length - 1,
+            });
+        }
+        hamming_distance = Some(weight as u32);
+        min_data_length = data_length;
+
+        if weight == 2 {
+            break;
+        }
+        zero_sets.limit(weight - 1);
+    }
+
+    rows.push(ProfileRow {
+        hamming_distance,
+        min_data_length,
+        max_data_length,
+    });
+
+    Some(HammingDistanceProfile { rows, max_weight })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::hamming_distance::{compute, profile, ProfileRow, MAX_WEIGHT};
+    use checksum_tapestry::crc::{BitOrder, BitWidth, CRCConfiguration};
+
+    /// Count the undetected error patterns of each weight by checking
+    /// every pattern of a short codeword
+    fn brute_force(generator: u64, width: u32, codeword_length: usize) -> Vec<u64> {
+        let mut syndromes: Vec<u64> = vec![1];
+        for _ in 1..codeword_length {
+            let last = syndromes[syndromes.len() - 1] << 1;
+            let syndrome = if (last >> width) & 1 == 1 {
+                last ^ generator
+            } else {
+                last
+            };
+            syndromes.push(syndrome);
+        }
+
+        let mut weights = vec![0; MAX_WEIGHT + 1];
+        for pattern in 1u64..(1 << codeword_length) {
+            let weight = pattern.count_ones() as usize;
+            if weight > MAX_WEIGHT {
+                continue;
+            }
+            let syndrome = (0..codeword_length)
+                .filter(|t| (pattern >> t) & 1 == 1)
+                .fold(0, |s, t| s ^ syndromes[t]);
+            if syndrome == 0 {
+                weights[weight] += 1;
+            }
+        }
+
+        weights
+    }
+
+    /// The counts match checking every error pattern
+    #[test]
+    fn compute_matches_brute_force() {
+        for (poly, data_length) in [(0x07, 12), (0x31, 10), (0x9B, 8), (0x2F, 12)] {
+            let configuration = CRCConfiguration::<u16>::new(
+                "CRC-8",
+                BitWidth::Eight,
+                BitOrder::MSBFirst,
+                poly,
+                false,
+                None,
+                None,
+            );
+            let result = compute(&configuration, data_length, MAX_WEIGHT).unwrap();
+
+            assert_eq!(result.codeword_length, data_length + 8);
+            assert_eq!(
+                result.weights,
+                brute_force(0x100 | poly as u64, 8, data_length + 8)
+            );
+        }
+    }
+
+    /// Koopman lists CRC-32 as HD=7 up to 171 bits, HD=6 up to 268
+    /// bits and HD=5 up to 2974 bits
+    #[test]
+    fn compute_works_crc32() {
+        let configuration = CRCConfiguration::<u32>::new(
+            "CRC-32/ISO-HDLC",
+            BitWidth::ThirtyTwo,
+            BitOrder::LSBFirst,
+            0x04C11DB7,
+            true,
+            Some(0xFFFFFFFF),
+            Some(0xFFFFFFFF),
+        );
+
+        assert_eq!(
+            compute(&configuration, 171, MAX_WEIGHT)
+                .unwrap()
+                .hamming_distance,
+            None
+        );
+        assert_eq!(
+            compute(&configuration, 172, MAX_WEIGHT)
+                .unwrap()
+                .hamming_distance,
+            Some(6)
+        );
+        let result = compute(&configuration, 269, MAX_WEIGHT).unwrap();
+        assert_eq!(result.hamming_distance, Some(5));
+        assert_eq!(result.weights[2..5], [0, 0, 0]);
+    }
+
+    /// Counting fewer weights gives the same counts for those weights
+    #[test]
+    fn compute_works_max_weight() {
+        let configuration = CRCConfiguration::<u32>::new(
+            "CRC-32/ISO-HDLC",
+            BitWidth::ThirtyTwo,
+            BitOrder::LSBFirst,
+            0x04C11DB7,
+            true,
+            Some(0xFFFFFFFF),
+            Some(0xFFFFFFFF),
+        );
+        let full = compute(&configuration, 269, MAX_WEIGHT).unwrap();
+
+        let result = compute(&configuration, 269, 4).unwrap();
+        assert_eq!(result.hamming_distance, None);
+        assert_eq!(result.weights, [0; 5]);
+
+        let result = compute(&configuration, 269, 5).unwrap();
+        assert_eq!(result.hamming_distance, Some(5));
+        assert_eq!(result.weights, full.weights[..6]);
+
+        let result = compute(&configuration, 269, MAX_WEIGHT + 1).unwrap();
+        assert_eq!(result.weights, full.weights);
+
+        let result = profile(&configuration, 3000, 4).unwrap();
+        assert_eq!(
+            result.rows,
+            [
+                ProfileRow {
+                    hamming_distance: None,
+                    min_data_length: 1,
+                    max_data_length: 2974,
+                },
+                ProfileRow {
+                    hamming_distance: Some(4),
+                    min_data_length: 2975,
+                    max_data_length: 3000,
+                },
+            ]
+        );
+        assert!(result.to_csv().contains(">4,1,2974\n"));
+    }
+
+    /// The profile agrees with the Hamming distance at every length
+    #[test]
+    fn profile_matches_compute() {
+        for poly in [0x07, 0x31, 0x9B, 0x2F] {
+            let configuration = CRCConfiguration::<u16>::new(
+                "CRC-8",
+                BitWidth::Eight,
+                BitOrder::MSBFirst,
+                poly,
+                false,
+                None,
+                None,
+            );
+            let result = profile(&configuration, 80, MAX_WEIGHT).unwrap();
+
+            assert_eq!(result.rows[0].min_data_length, 1);
+            assert_eq!(result.rows.last().unwrap().max_data_length, 80);
+            for row in &result.rows {
+                for data_length in row.min_data_length..=row.max_data_length {
+                    let hd = compute(&configuration, data_length, MAX_WEIGHT).unwrap();
+                    assert_eq!(hd.hamming_distance, row.hamming_distance);
+                }
+            }
+            for (row, next) in result.rows.iter().zip(result.rows.iter().skip(1)) {
+                assert_eq!(row.max_data_length + 1, next.min_data_length);
+            }
+        }
+    }
+
+    /// The CRC-32 breakpoints match Koopman's table
+    #[test]
+    fn profile_works_crc32() {
+        let configuration = CRCConfiguration::<u32>::new(
+            "CRC-32/ISO-HDLC",
+            BitWidth::ThirtyTwo,
+            BitOrder::LSBFirst,
+            0x04C11DB7,
+            true,
+            Some(0xFFFFFFFF),
+            Some(0xFFFFFFFF),
+        );
+        let result = profile(&configuration, 3000, MAX_WEIGHT).unwrap();
+
+        let expected = [(None, 1, 171), (Some(6), 172, 268), (Some(5), 269, 2974)];
+        for (row, (hd, min, max)) in result.rows.iter().zip(expected) {
+            assert_eq!(
+                *row,
+                ProfileRow {
+                    hamming_distance: hd,
+                    min_data_length: min,
+                    max_data_length: max,
+                }
+            );
+        }
+        assert_eq!(result.rows.len(), 4);
+        assert_eq!(result.rows[3].hamming_distance, Some(4));
+
+        assert_eq!(
+            result
+                .to_string()
+                .lines()
+                .nth(1)
+                .unwrap()
+                .split_whitespace()
+                .collect::<Vec<_>>(),
+            [">6", "1", "171"]
+        );
+        assert_eq!(result.to_csv().lines().nth(2).unwrap(), "6,172,268");
+    }
+
+    /// Polynomials without a +1 term and empty data words can't be
+    /// checked
+    #[test]
+    fn compute_fails() {
+        let configuration = CRCConfiguration::<u16>::new(
+            "CRC-16/EVEN",
+            BitWidth::Sixteen,
+            BitOrder::MSBFirst,
+            0x8004,
+            false,
+            None,
+            None,
+        );
+        assert!(compute(&configuration, 8, MAX_WEIGHT).is_none());
+        assert!(profile(&configuration, 8, MAX_WEIGHT).is_none());
+
+        let configuration = CRCConfiguration::<u16>::new(
+            "CRC-16/ARC",
+            BitWidth::Sixteen,
+            BitOrder::LSBFirst,
+            0x8005,
+            true,
+            None,
+            None,
+        );
+        assert!(compute(&configuration, 0, MAX_WEIGHT).is_none());
+        assert!(profile(&configuration, 0, MAX_WEIGHT).is_none());
+        assert!(compute(&configuration, 8, 1).is_none());
+        assert!(profile(&configuration, 8, 1).is_none());
+    }
+}
@@ -3,7 +3,7 @@
 //! This includes basic data structures and functions for binning data
 //! and functions for plotting the data on a terminal.
 
-use crate::analysis::experiment::Experiment;
+use crate::analysis::experiment::{Experiment, OutputWidth};
 
 /// A histogram that only contains the count of data in each bin
 /// Contains experiment data in a set of bins
@@ -14,6 +14,42 @@ pub struct SimpleHistogram {
     pub bins: Vec<u32>,
     /// Number of data points
     pub num_data_points: u32,
+    /// The width of the binned values in bits
+    /// The bins evenly split the range 0 to 2^bits - 1
+    /// Widths outside 1 to 64 are clamped to that range when drawing
+    pub bits: u32,
+}
+
+impl SimpleHistogram {
+    /// Create a histogram from counts that are already binned, for
+    /// values of the given width in bits
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than 255 bins or the counts add up to
+    /// more than u32::MAX.
+    ///
+    /// # Examples
+    /// ```
+    /// use tapestry_analysis::analysis::histogram::SimpleHistogram;
+    ///
+    /// let histogram = SimpleHistogram::from_bins(vec![22, 21, 22, 27, 22, 36], 8);
+    /// assert_eq!(histogram.num_bins, 6);
+    /// assert_eq!(histogram.num_data_points, 150);
+    /// ```
+    pub fn from_bins(bins: Vec<u32>, bits: u32) -> SimpleHistogram {
+        SimpleHistogram {
+            num_bins: bins.len().try_into().unwrap(),
+            num_data_points: bins
+                .iter()
+                .map(|b| *b as u64)
+                .sum::<u64>()
+                .try_into()
+                .unwrap(),
+            bins,
+            bits,
+        }
+    }
 }
 
 /// A more complicated histogram that contains the actual values in
@@ -23,6 +59,8 @@ pub struct FullHistogram {}
 /// Functions a histogram should implement
 pub trait Histogram {
     /// Bin an experiment
+    /// The range of the bins comes from the output type of the
+    /// experiment, so a 16-bit checksum is binned over 0 to 0xFFFF.
     ///
     /// # Examples
     /// ```
@@ -44,7 +82,7 @@ pub trait Histogram {
     /// let histogram = SimpleHistogram::new(&adler32_experiment, 10);
     /// assert_eq!(histogram.bins.len(), 10);
     /// ```
-    fn new(experiment: &Experiment<u32>, num_bins: u8) -> Self;
+    fn new<T: OutputWidth>(experiment: &Experiment<T>, num_bins: u8) -> Self;
 
     /// Draw the histogram on a terminal
     /// This function has side effects
@@ -72,12 +110,13 @@ pub trait Histogram {
 }
 
 impl Histogram for SimpleHistogram {
-    fn new(experiment: &Experiment<u32>, num_bins: u8) -> Self {
-        let bin_boundary = num_bins as f32 / u32::MAX as f32;
+    fn new<T: OutputWidth>(experiment: &Experiment<T>, num_bins: u8) -> Self {
         let mut bins: Vec<u32> = vec![0; num_bins.into()];
 
-        for i in 0..experiment.samples.len() {
-            let bin = (experiment.samples[i].sample as f32 * bin_boundary).floor();
+        for sample in &experiment.samples {
+            // Scale with integers so the largest output lands in the
+            // last bin for every width
+            let bin = (sample.sample.to_u64() as u128 * num_bins as u128) >> T::BITS;
             bins[bin as usize] += 1;
         }
 
@@ -85,6 +124,7 @@ impl Histogram for SimpleHistogram {
             num_bins,
             bins,
             num_data_points: experiment.samples.len().try_into().unwrap(),
+            bits: T::BITS,
         }
     }
 
@@ -99,11 +139,19 @@ impl Histogram for SimpleHistogram {
         let avg_stars_per_bin = avg_stars_per_bin * 1.8;
         let line_div = avg_stars_per_bin / width as f32;
 
+        // Hex digits needed to show the largest value
+        let bits = self.bits.clamp(1, 64);
+        let digits = bits.div_ceil(4) as usize;
+        let max = (u64::MAX >> (64 - bits)) as f64;
+
         for i in 0..self.num_bins {
             let total = self.bins[i as usize];
-            let start = (u32::MAX as f32 / self.num_bins as f32) * i as f32;
-            let end = (u32::MAX as f32 / self.num_bins as f32) * (i + 1) as f32;
-            print!("0x{:08X} - 0x{:08X}: ", start as u32, end as u32);
+            let start = (max / self.num_bins as f64) * i as f64;
+            let end = (max / self.num_bins as f64) * (i + 1) as f64;
+            print!(
+                "0x{:0digits$X} - 0x{:0digits$X}: ",
+                start as u64, end as u64
+            );
             let stars_to_print: u32 = (total as f32 / line_div).floor() as u32;
             for _j in 0..stars_to_print {
                 print!("*");
@@ -119,6 +167,7 @@ mod tests {
         distribution::DiscreteUniformDistribution,
         experiment::Experiment,
         histogram::{Histogram, SimpleHistogram},
+        sample::Sample,
     };
     use checksum_tapestry::adler32::Adler32;
 
@@ -150,4 +199,34 @@ mod tests {
         // draw the data
         histogram.draw_terminal();
     }
+
+    /// Bins cover the output space of the experiment's type
+    #[test]
+    fn new_works_output_width() {
+        let samples = [0x00u8, 0x3F, 0x40, 0x7F, 0x80, 0xFF]
+            .iter()
+            .map(|d| Sample { sample: *d })
+            .collect();
+        let experiment: Experiment<u8> = Experiment::from_samples(samples);
+
+        let histogram = SimpleHistogram::new(&experiment, 4);
+        assert_eq!(histogram.bits, 8);
+        assert_eq!(histogram.bins, vec![2, 2, 1, 1]);
+
+        // The largest output goes in the last bin
+        let experiment: Experiment<u32> =
+            Experiment::from_samples(vec![Sample { sample: u32::MAX }]);
+        let histogram = SimpleHistogram::new(&experiment, 10);
+        assert_eq!(histogram.bins[9], 1);
+        histogram.draw_terminal();
+    }
+
+    /// Drawing doesn't panic on a width set out of range
+    #[test]
+    fn draw_terminal_works_bits_out_of_range() {
+        for bits in [0, 65, u32::MAX] {
+            let histogram = SimpleHistogram::from_bins(vec![1, 1], bits);
+            histogram.draw_terminal();
+        }
+    }
 }
@@ -5,18 +5,23 @@
 //! Because the primary purpose of this crate is analyzing checksum
 //! and hash algorithms, the code is tailored to testing a uniform
 //! distribution.
-//! The data can be transformed to test other distributions if needed.
+//! Other distributions can be tested with [`cdf_statistic`] and any
+//! type implementing [`Cdf`].
 #![warn(missing_docs)]
 #![warn(unsafe_code)]
 
+use std::cmp::Ordering;
+
 use crate::analysis::{
-    distribution::{normalize_variable, CriticalValue, DiscreteUniformDistributionParameters},
-    experiment::Experiment,
+    distribution::{Cdf, CriticalValue, DiscreteUniformDistributionParameters},
+    experiment::{Experiment, OutputWidth},
+    special::ln_gamma,
 };
 
 /// Compute the Kolmogoro Smirnov distribution quantiles or tails
 ///
 /// Values below 41 are not included due to precision concerns.
+/// Use [`p_value`] for smaller samples.
 ///
 /// These are values for the two-sided Komogoro Smirnov distribution
 ///
@@ -59,6 +64,335 @@ pub fn critical_value(cv: CriticalValue, n: u32) -> Option<f32> {
     }
 }
 
+/// Compute the two-sided p-value for a Kolmogorov–Smirnov statistic
+///
+/// This is the probability of seeing a statistic at least as large
+/// as the given one with n samples, if the data comes from the
+/// expected distribution.  Unlike [`critical_value`], this works for
+/// any n.
+///
+/// The method is chosen by sample size, following Simard and
+/// L'Ecuyer:
+/// * n <= 140 uses the exact Marsaglia-Tsang-Wang method
+/// * larger n uses the Pelz-Good asymptotic series
+/// * when the statistic is far in the tail (n * d^2 >= 18) the upper
+///   tail is computed directly so small p-values aren't lost to
+///   rounding, using twice the exact one-sided tail for n <= 140 and
+///   the Kolmogorov limiting distribution for larger n
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::ks::p_value;
+///
+/// let p = p_value(0.274, 10);
+/// assert!(f32::abs(p - 0.3715) < 0.0001);
+/// ```
+// The equivalent using the Python SciPy package is:
+// stats.kstwo.sf(d, n)
+pub fn p_value(statistic: f32, n: u32) -> f32 {
+    let d = statistic as f64;
+
+    if n == 0 || d <= 0.0 {
+        return 1.0;
+    }
+    if d >= 1.0 {
+        return 0.0;
+    }
+
+    let nd2 = n as f64 * d * d;
+    let p = if n <= 140 && nd2 >= 18.0 {
+        2.0 * smirnov_sf(d, n)
+    } else if n <= 140 {
+        1.0 - exact_cdf(d, n)
+    } else if nd2 >= 18.0 {
+        kolmogorov_limit_sf(f64::sqrt(n as f64) * d)
+    } else {
+        1.0 - pelz_good_cdf(d, n)
+    };
+
+    p.clamp(0.0, 1.0) as f32
+}
+
+/// Compute the exact CDF of the two-sided Kolmogorov–Smirnov
+/// statistic, P(D_n < d)
+///
+/// This uses the matrix method of Marsaglia, Tsang and Wang, based on
+/// Durbin's formula.  It raises a (2k - 1) x (2k - 1) matrix to the
+/// nth power, where k = floor(n * d) + 1, so it gets slow for large
+/// n and d.
+///
+/// Marsaglia, G., Tsang, W. W., & Wang, J. (2003).
+/// Evaluating Kolmogorov's Distribution.
+/// Journal of Statistical Software, 8(18), 1–4.
+pub fn exact_cdf(d: f64, n: u32) -> f64 {
+    let n_f = n as f64;
+    let k = (n_f * d).floor() as usize + 1;
+    let m = 2 * k - 1;
+    let h = k as f64 - n_f * d;
+
+    let mut hm = vec![vec![0.0; m]; m];
+    for (i, row) in hm.iter_mut().enumerate() {
+        for (j, item) in row.iter_mut().enumerate() {
+            if i + 1 >= j {
+                *item = 1.0;
+            }
+        }
+    }
+    for (i, row) in hm.iter_mut().enumerate() {
+        row[0] -= h.powi(i as i32 + 1);
+    }
+    for (i, item) in hm[m - 1].iter_mut().enumerate() {
+        *item -= h.powi((m - i) as i32);
+    }
+    if 2.0 * h - 1.0 > 0.0 {
+        hm[m - 1][0] += (2.0 * h - 1.0).powi(m as i32);
+    }
+    for (i, row) in hm.iter_mut().enumerate() {
+        for (j, item) in row.iter_mut().enumerate() {
+            if i + 1 > j {
+                for g in 1..=(i + 1 - j) {
+                    *item /= g as f64;
+                }
+            }
+        }
+    }
+
+    let (q, mut exponent) = matrix_power(&hm, n);
+
+    let mut s = q[k - 1][k - 1];
+    for i in 1..=n {
+        s *= i as f64 / n_f;
+        if s < 1.0e-140 {
+            s *= 1.0e140;
+            exponent -= 140;
+        }
+    }
+
+    s * 10.0_f64.powi(exponent)
+}
+
+/// Multiply two square matrices
+fn matrix_multiply(a: &[Vec<f64>], b: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let m = a.len();
+    let mut c = vec![vec![0.0; m]; m];
+
+    for i in 0..m {
+        for k in 0..m {
+            let aik = a[i][k];
+            if aik == 0.0 {
+                continue;
+            }
+            for j in 0..m {
+                c[i][j] += aik * b[k][j];
+            }
+        }
+    }
+
+    c
+}
+
+/// Raise a square matrix to the nth power by repeated squaring
+///
+/// The entries can overflow an f64 for large n, so the result is
+/// returned as a matrix and a power of ten it should be multiplied by.
+fn matrix_power(a: &[Vec<f64>], n: u32) -> (Vec<Vec<f64>>, i32) {
+    if n == 1 {
+        return (a.to_vec(), 0);
+    }
+
+    let (half, half_exponent) = matrix_power(a, n / 2);
+    let mut result = matrix_multiply(&half, &half);
+    let mut exponent = 2 * half_exponent;
+
+    if n % 2 == 1 {
+        result = matrix_multiply(a, &result);
+    }
+
+    let center = result.len() / 2;
+    if result[center][center] > 1.0e140 {
+        for row in result.iter_mut() {
+            for item in row.iter_mut() {
+                *item *= 1.0e-140;
+            }
+        }
+        exponent += 140;
+    }
+
+    (result, exponent)
+}
+
+/// Compute the CDF of the two-sided Kolmogorov–Smirnov statistic,
+/// P(D_n < d), using the Pelz-Good asymptotic series
+///
+/// This is accurate to around five digits for n > 140.
+///
+/// Pelz, W., & Good, I. J. (1976).
+/// Approximating the Lower Tail-Areas of the Kolmogorov-Smirnov
+/// One-Sample Statistic.
+/// Journal of the Royal Statistical Society, Series B, 38(2), 152–156.
+pub fn pelz_good_cdf(d: f64, n: u32) -> f64 {
+    const JMAX: i32 = 20;
+    const EPS: f64 = 1.0e-10;
+    // sqrt(2 pi) and sqrt(pi / 2)
+    const C: f64 = 2.506_628_274_631_001;
+    const C2: f64 = 1.253_314_137_315_500_1;
+    let pi2 = std::f64::consts::PI * std::f64::consts::PI;
+    let pi4 = pi2 * pi2;
+
+    let n_f = n as f64;
+    let rn = f64::sqrt(n_f);
+    let z = rn * d;
+    let z2 = z * z;
+    let z4 = z2 * z2;
+    let z6 = z4 * z2;
+    let w = pi2 / (2.0 * z2);
+
+    let mut sum = 0.0;
+    for j in 0..=JMAX {
+        let ti = j as f64 + 0.5;
+        let term = f64::exp(-ti * ti * w);
+        sum += term;
+        if term <= EPS * sum {
+            break;
+        }
+    }
+    sum *= C / z;
+
+    let mut tom = 0.0;
+    for j in 0..=JMAX {
+        let ti = j as f64 + 0.5;
+        let term = (pi2 * ti * ti - z2) * f64::exp(-ti * ti * w);
+        tom += term;
+        if f64::abs(term) <= EPS * f64::abs(tom) {
+            break;
+        }
+    }
+    sum += tom * C2 / (rn * 3.0 * z4);
+
+    let mut tom = 0.0;
+    for j in 0..=JMAX {
+        let ti = j as f64 + 0.5;
+        let ti2 = ti * ti;
+        let term = (6.0 * z6
+            + 2.0 * z4
+            + pi2 * (2.0 * z4 - 5.0 * z2) * ti2
+            + pi4 * (1.0 - 2.0 * z2) * ti2 * ti2)
+            * f64::exp(-ti2 * w);
+        tom += term;
+        if f64::abs(term) <= EPS * f64::abs(tom) {
+            break;
+        }
+    }
+    sum += tom * C2 / (n_f * 36.0 * z * z6);
+
+    let mut tom = 0.0;
+    for j in 1..=JMAX {
+        let ti2 = (j * j) as f64;
+        let term = pi2 * ti2 * f64::exp(-ti2 * w);
+        tom += term;
+        if term <= EPS * tom {
+            break;
+        }
+    }
+    sum -= tom * C2 / (n_f * 18.0 * z * z2);
+
+    let mut tom = 0.0;
+    for j in 0..=JMAX {
+        let ti = j as f64 + 0.5;
+        let ti2 = ti * ti;
+        let term = (-30.0 * z6 - 90.0 * z6 * z2
+            + pi2 * (135.0 * z4 - 96.0 * z6) * ti2
+            + pi4 * (212.0 * z4 - 60.0 * z2) * ti2 * ti2
+            + pi2 * pi4 * ti2 * ti2 * ti2 * (5.0 - 30.0 * z2))
+            * f64::exp(-ti2 * w);
+        tom += term;
+        if f64::abs(term) <= EPS * f64::abs(tom) {
+            break;
+        }
+    }
+    sum += tom * C2 / (rn * n_f * 3240.0 * z4 * z6);
+
+    let mut tom = 0.0;
+    for j in 1..=JMAX {
+        let ti2 = (j * j) as f64;
+        let term = (3.0 * pi2 * ti2 * z2 - pi4 * ti2 * ti2) * f64::exp(-ti2 * w);
+        tom += term;
+        if f64::abs(term) <= EPS * f64::abs(tom) {
+            break;
+        }
+    }
+    sum += tom * C2 / (rn * n_f * 108.0 * z6);
+
+    sum
+}
+
+/// Compute the exact upper tail of the one-sided Kolmogorov–Smirnov
+/// statistic, P(D+_n >= d)
+///
+/// This is the Smirnov-Birnbaum-Tingey formula.  The two-sided tail is
+/// twice this less the chance both D+ and D- are at least d, which is
+/// negligible in the far tail and zero for d >= 0.5.
+///
+/// Birnbaum, Z. W., & Tingey, F. H. (1951).
+/// One-Sided Confidence Contours for Probability Distribution
+/// Functions.
+/// The Annals of Mathematical Statistics, 22(4), 592–596.
+pub fn smirnov_sf(d: f64, n: u32) -> f64 {
+    if d <= 0.0 {
+        return 1.0;
+    }
+    if d >= 1.0 {
+        return 0.0;
+    }
+
+    let n_f = n as f64;
+    let ln_n_factorial = ln_gamma(n_f + 1.0);
+    let last = (n_f * (1.0 - d)).floor() as u32;
+
+    // Sum in logs, as the terms can be far below the smallest f64
+    let sum: f64 = (0..=last)
+        .filter_map(|j| {
+            let j_f = j as f64;
+            let a = 1.0 - d - j_f / n_f;
+            (a > 0.0).then(|| {
+                f64::exp(
+                    ln_n_factorial - ln_gamma(j_f + 1.0) - ln_gamma(n_f - j_f + 1.0)
+                        + (n_f - j_f) * a.ln()
+                        + (j_f - 1.0) * (d + j_f / n_f).ln()
+                        + d.ln(),
+                )
+            })
+        })
+        .sum();
+
+    sum.clamp(0.0, 1.0)
+}
+
+/// Compute the upper tail of the Kolmogorov limiting distribution,
+/// P(K > x), where K is the limit of sqrt(n) * D_n
+///
+/// Q(x) = 2 * sum_{k=1}^{inf} (-1)^(k-1) e^(-2 k^2 x^2)
+pub fn kolmogorov_limit_sf(x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for k in 1..=100 {
+        let k = k as f64;
+        let term = f64::exp(-2.0 * k * k * x * x);
+        sum += sign * term;
+        if term < 1.0e-16 * f64::abs(sum) {
+            break;
+        }
+        sign = -sign;
+    }
+
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
 /// Calculate the shifted expected uniform CDF distributions to
 /// compare to the observed distribution.
 /// This calculates "upper" and "lower" distributions.
@@ -106,7 +440,7 @@ pub fn shifted_uniform_cdf_distribution(n: u32) -> (Vec<f32>, Vec<f32>) {
 ///
 /// let samples: Vec<Sample<f32>> = data.iter().map(|d| Sample { sample: *d }).collect();
 ///
-/// let experiment: Experiment<f32> = Experiment { samples };
+/// let experiment: Experiment<f32> = Experiment::from_samples(samples);
 /// let parameters = DiscreteUniformDistributionParameters { a: 0, b: 2 };
 /// let statistic = statistic(experiment, &parameters);
 /// assert!(f32::abs(statistic - 0.195) < 0.0001);
@@ -115,6 +449,39 @@ pub fn statistic(
     experiment: Experiment<f32>,
     parameters: &DiscreteUniformDistributionParameters,
 ) -> f32 {
+    cdf_statistic(experiment, parameters)
+}
+
+/// Calculate the Kolmogorov–Smirnov test statistic against any CDF
+/// This finds the maximum absolute difference between the given CDF
+/// and the empirical CDF of the experiment.
+///
+/// Tied samples are treated as a single step in the empirical CDF.
+/// For discrete distributions, both sides of each step are compared
+/// using [`Cdf::cdf_left`].  The p-values from [`p_value`] assume a
+/// continuous distribution, so they are conservative for discrete
+/// ones.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{
+///     distribution::PoissonDistributionParameters,
+///     experiment::Experiment,
+///     ks::cdf_statistic,
+///     sample::Sample,
+/// };
+///
+/// let data: [f32; 10] = [0.0, 1.0, 1.0, 2.0, 2.0, 2.0, 3.0, 3.0, 4.0, 6.0];
+///
+/// let samples: Vec<Sample<f32>> = data.iter().map(|d| Sample { sample: *d }).collect();
+///
+/// let experiment: Experiment<f32> = Experiment::from_samples(samples);
+/// let parameters = PoissonDistributionParameters { lambda: 2.5 };
+/// let statistic = cdf_statistic(experiment, &parameters);
+/// assert!(f32::abs(statistic - 0.0580) < 0.0001);
+/// ```
+pub fn cdf_statistic<C: Cdf + ?Sized>(experiment: Experiment<f32>, cdf: &C) -> f32 {
     let mut samples = experiment.samples;
 
     samples.sort();
@@ -122,33 +489,143 @@ pub fn statistic(
 
     let n = sorted_data.len();
 
-    let (uniform_cdf_shifted_minus, uniform_cdf_shifted_plus) =
-        shifted_uniform_cdf_distribution(n as u32);
+    let mut max: f32 = 0.0;
+    let mut i = 0;
+
+    while i < n {
+        let x = sorted_data[i];
+
+        // Skip over any ties, the empirical CDF jumps once for all of them
+        let mut j = i + 1;
+        while j < n && sorted_data[j] == x {
+            j += 1;
+        }
 
-    let mut interpolated_values: Vec<f32> = Vec::new();
+        let below = f32::abs(i as f32 / n as f32 - cdf.cdf_left(x));
+        let above = f32::abs(j as f32 / n as f32 - cdf.cdf(x));
+        max = f32::max(max, f32::max(below, above));
 
-    for item in sorted_data {
-        interpolated_values.push(normalize_variable(item, parameters));
+        i = j;
     }
 
-    let mut minus_max: f32 = 0.0;
-    let mut plus_max: f32 = 0.0;
+    max
+}
 
-    for i in 0..n {
-        let res = f32::abs(uniform_cdf_shifted_minus[i] - interpolated_values[i]);
-        if res > minus_max {
-            minus_max = res;
-        }
+/// The result of a two-sample Kolmogorov–Smirnov test
+#[derive(Debug)]
+pub struct TwoSampleTest {
+    /// The maximum absolute difference between the two empirical CDFs
+    pub statistic: f32,
+    /// The effective sample size, n * m / (n + m)
+    pub effective_n: f32,
+    /// The two-sided p-value for the hypothesis that both experiments
+    /// come from the same distribution
+    pub p_value: f32,
+}
+
+/// Sample values the two-sample test can compare
+///
+/// Checksum outputs of every width are compared as integers, so 64-bit
+/// outputs don't lose precision, and floating point samples are
+/// compared with their total order, as in
+/// [`Sample`](crate::analysis::sample::Sample).
+pub trait TwoSampleValue: Copy {
+    /// Compare two values
+    fn compare(&self, other: &Self) -> Ordering;
+}
+
+impl<T: OutputWidth> TwoSampleValue for T {
+    fn compare(&self, other: &Self) -> Ordering {
+        self.to_u64().cmp(&other.to_u64())
+    }
+}
+
+impl TwoSampleValue for f32 {
+    fn compare(&self, other: &Self) -> Ordering {
+        self.total_cmp(other)
+    }
+}
+
+/// Perform a two-sample Kolmogorov–Smirnov test
+///
+/// This tests whether two experiments come from the same
+/// distribution, without knowing what that distribution is.  For
+/// example, whether two checksums have the same output distribution
+/// on the same message source.  The experiments can have different
+/// sizes.
+///
+/// The p-value uses the one-sample distribution with the effective
+/// sample size rounded to the nearest integer, which is the same
+/// approximation SciPy's ks_2samp uses in asymptotic mode.
+///
+/// Returns None if either experiment is empty.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{experiment::Experiment, ks::two_sample_test, sample::Sample};
+///
+/// let a: Experiment<u32> =
+///     Experiment::from_samples((0..50).map(|i| Sample { sample: i * 2 }).collect());
+/// let b: Experiment<u32> =
+///     Experiment::from_samples((0..50).map(|i| Sample { sample: i * 2 + 60 }).collect());
+///
+/// let result = two_sample_test(&a, &b).unwrap();
+/// assert_eq!(result.statistic, 0.6);
+/// assert_eq!(result.effective_n, 25.0);
+/// assert!(result.p_value < 0.0001);
+/// ```
+// The equivalent using the Python SciPy package is:
+// stats.ks_2samp(a, b, method='asymp')
+pub fn two_sample_test<T: TwoSampleValue>(
+    a: &Experiment<T>,
+    b: &Experiment<T>,
+) -> Option<TwoSampleTest> {
+    if a.samples.is_empty() || b.samples.is_empty() {
+        return None;
     }
 
-    for i in 0..n {
-        let res = f32::abs(uniform_cdf_shifted_plus[i] - interpolated_values[i]);
-        if res > plus_max {
-            plus_max = res;
+    let sorted = |experiment: &Experiment<T>| {
+        let mut data: Vec<T> = experiment.samples.iter().map(|s| s.sample).collect();
+        data.sort_by(|x, y| x.compare(y));
+        data
+    };
+    let a_data = sorted(a);
+    let b_data = sorted(b);
+
+    let n = a_data.len();
+    let m = b_data.len();
+
+    // Walk both sorted lists together, moving past every copy of the
+    // smallest remaining value so ties are handled correctly
+    let mut i = 0;
+    let mut j = 0;
+    let mut max: f64 = 0.0;
+
+    while i < n && j < m {
+        let x = match a_data[i].compare(&b_data[j]) {
+            Ordering::Greater => b_data[j],
+            _ => a_data[i],
+        };
+        while i < n && a_data[i].compare(&x) == Ordering::Equal {
+            i += 1;
+        }
+        while j < m && b_data[j].compare(&x) == Ordering::Equal {
+            j += 1;
         }
+
+        let difference = f64::abs(i as f64 / n as f64 - j as f64 / m as f64);
+        max = f64::max(max, difference);
     }
 
-    f32::max(minus_max, plus_max)
+    let statistic = max as f32;
+    let effective_n = (n * m) as f64 / (n + m) as f64;
+
+    Some(TwoSampleTest {
+        statistic,
+        effective_n: effective_n as f32,
+        p_value: p_value(statistic, effective_n.round() as u32),
+    })
 }
 
 /// Test examples comes from several sources, including:
@@ -157,9 +634,15 @@ pub fn statistic(
 #[cfg(test)]
 mod tests {
     use crate::analysis::{
-        distribution::{CriticalValue, DiscreteUniformDistributionParameters},
+        distribution::{
+            CriticalValue, DiscreteUniformDistributionParameters, IrwinHallDistributionParameters,
+            PoissonDistributionParameters,
+        },
         experiment::Experiment,
-        ks::{critical_value, shifted_uniform_cdf_distribution, statistic},
+        ks::{
+            cdf_statistic, critical_value, exact_cdf, kolmogorov_limit_sf, p_value, pelz_good_cdf,
+            shifted_uniform_cdf_distribution, smirnov_sf, statistic, two_sample_test,
+        },
         sample::Sample,
     };
 
@@ -173,7 +656,7 @@ mod tests {
 
         let samples: Vec<Sample<f32>> = data.iter().map(|d| Sample { sample: *d }).collect();
 
-        let experiment: Experiment<f32> = Experiment { samples };
+        let experiment: Experiment<f32> = Experiment::from_samples(samples);
         let parameters = DiscreteUniformDistributionParameters { a: 0, b: 2 };
         let statistic = statistic(experiment, &parameters);
 
@@ -236,7 +719,7 @@ mod tests {
 
         let samples: Vec<Sample<f32>> = data.iter().map(|d| Sample { sample: *d }).collect();
 
-        let experiment: Experiment<f32> = Experiment { samples };
+        let experiment: Experiment<f32> = Experiment::from_samples(samples);
         let parameters = DiscreteUniformDistributionParameters { a: 0, b: 2 };
         let statistic = statistic(experiment, &parameters);
 
@@ -256,7 +739,7 @@ mod tests {
                 println!("Data does not follow a uniform distribution");
             }
         } else {
-            assert!(false);
+            unreachable!();
         }
     }
 
@@ -274,13 +757,13 @@ mod tests {
 
         let mut total: f32 = 0.0;
         for s in &uniform_cdf_minus {
-            total += *s as f32;
+            total += *s;
         }
         let ucm_mean: f32 = total / n as f32;
 
         let mut total: f32 = 0.0;
         for s in &uniform_cdf_plus {
-            total += *s as f32;
+            total += *s;
         }
         let ucp_mean: f32 = total / n as f32;
 
@@ -310,4 +793,202 @@ mod tests {
         assert_eq!(ucm_last, 0.99999);
         assert_eq!(ucp_last, 1.0);
     }
+
+    /// Test the exact CDF against values computed with exact rational
+    /// arithmetic
+    #[test]
+    fn exact_cdf_works() {
+        assert!(f64::abs(exact_cdf(0.274, 10) - 0.628_479_615_456_504) < 1.0e-12);
+        assert!(f64::abs(exact_cdf(0.145, 8) - 0.013_686_259_657_030) < 1.0e-12);
+        assert!(f64::abs(exact_cdf(0.25, 40) - 0.989_104_479_131_448) < 1.0e-12);
+        assert!(f64::abs(exact_cdf(0.1, 100) - 0.747_307_242_993_610) < 1.0e-12);
+    }
+
+    /// The Pelz-Good approximation should agree with the exact CDF for
+    /// larger n
+    #[test]
+    fn pelz_good_cdf_works() {
+        assert!(f64::abs(pelz_good_cdf(0.1, 140) - exact_cdf(0.1, 140)) < 1.0e-6);
+        assert!(f64::abs(pelz_good_cdf(0.05, 500) - exact_cdf(0.05, 500)) < 1.0e-6);
+    }
+
+    /// Test the limiting distribution at its 5% point
+    #[test]
+    fn kolmogorov_limit_sf_works() {
+        assert!(f64::abs(kolmogorov_limit_sf(1.358_099) - 0.05) < 1.0e-5);
+        assert_eq!(kolmogorov_limit_sf(0.0), 1.0);
+    }
+
+    /// Test p-values for small samples, where critical_value returns None
+    #[test]
+    fn p_value_small_n_works() {
+        // Rohatgi and Saleh table value for n = 8, alpha = 0.05
+        assert!(f32::abs(p_value(0.454, 8) - 0.05) < 0.005);
+
+        let p = p_value(0.5, 20);
+        assert!(f32::abs(p - 3.787_595e-5) < 1.0e-9);
+    }
+
+    /// Small p-values for small samples shouldn't round to zero
+    #[test]
+    fn p_value_small_n_far_tail_works() {
+        let p = p_value(0.6, 60);
+        assert!(f32::abs(p / 3.478_45e-21 - 1.0) < 1.0e-4);
+
+        let p = p_value(0.95, 20);
+        assert!(p > 0.0);
+        assert!(f32::abs(p / 1.907_349e-26 - 1.0) < 1.0e-3);
+    }
+
+    /// The exact one-sided tail agrees with the exact two-sided CDF,
+    /// where they're equal for d >= 0.5
+    #[test]
+    fn smirnov_sf_works() {
+        assert!(f64::abs(2.0 * smirnov_sf(0.5, 20) - 3.787_595_240_539_03e-5) < 1.0e-15);
+        assert!(f64::abs(2.0 * smirnov_sf(0.8, 10) - 2.208e-7) < 1.0e-17);
+        assert!(f64::abs(2.0 * smirnov_sf(0.6, 30) - (1.0 - exact_cdf(0.6, 30))) < 1.0e-12);
+        assert_eq!(smirnov_sf(0.0, 10), 1.0);
+        assert_eq!(smirnov_sf(1.0, 10), 0.0);
+    }
+
+    /// Test p-values for large samples, using Pelz-Good and the
+    /// limiting distribution
+    #[test]
+    fn p_value_large_n_works() {
+        assert!(f32::abs(p_value(0.04, 1000) - 0.079_339_56) < 1.0e-6);
+        assert!(f32::abs(p_value(0.05, 2000) - 8.743_096e-5) < 1.0e-8);
+        assert!(p_value(0.2, 1000) < 1.0e-16);
+    }
+
+    /// Test p-values at the edges of the statistic range
+    #[test]
+    fn p_value_edges_works() {
+        assert_eq!(p_value(0.0, 100), 1.0);
+        assert_eq!(p_value(1.0, 100), 0.0);
+        assert_eq!(p_value(0.5, 0), 1.0);
+    }
+
+    /// The p-value at the tabulated critical value should be close to
+    /// the significance level
+    #[test]
+    fn p_value_matches_critical_value() {
+        let n = 1000;
+        let cv = critical_value(CriticalValue::FivePercent, n).unwrap();
+        assert!(f32::abs(p_value(cv, n) - 0.05) < 0.005);
+    }
+
+    /// Test the generic statistic against a discrete distribution with
+    /// ties
+    #[test]
+    fn cdf_statistic_works_poisson() {
+        let data: [f32; 10] = [0.0, 1.0, 1.0, 2.0, 2.0, 2.0, 3.0, 3.0, 4.0, 6.0];
+
+        let samples: Vec<Sample<f32>> = data.iter().map(|d| Sample { sample: *d }).collect();
+
+        let experiment: Experiment<f32> = Experiment::from_samples(samples);
+        let parameters = PoissonDistributionParameters { lambda: 2.5 };
+        let statistic = cdf_statistic(experiment, &parameters);
+
+        // The largest gap is just below the step at 6:
+        // the empirical CDF is 0.9 and F(5) = 0.9580
+        assert!(f32::abs(statistic - 0.0580) < 0.0001);
+    }
+
+    /// Test the generic statistic against the Irwin–Hall distribution
+    #[test]
+    fn cdf_statistic_works_irwin_hall() {
+        // Sums of two uniforms, the triangular distribution
+        let data: [f32; 8] = [0.31, 0.62, 0.85, 1.02, 1.11, 1.27, 1.44, 1.73];
+
+        let samples: Vec<Sample<f32>> = data.iter().map(|d| Sample { sample: *d }).collect();
+
+        let experiment: Experiment<f32> = Experiment::from_samples(samples);
+        let parameters = IrwinHallDistributionParameters { n: 2 };
+        let statistic = cdf_statistic(experiment, &parameters);
+
+        assert!(f32::abs(statistic - 0.1448) < 0.0001);
+    }
+
+    /// Test the two-sample statistic with different sample sizes
+    #[test]
+    fn two_sample_test_works() {
+        let a: Experiment<f32> = Experiment::from_samples(
+            [
+                0.61, 0.29, 0.06, 0.59, -1.73, -0.74, 0.51, -0.56, 0.39, 1.64,
+            ]
+            .iter()
+            .map(|d| Sample { sample: *d })
+            .collect(),
+        );
+        let b: Experiment<f32> = Experiment::from_samples(
+            [
+                2.20, 1.66, 1.38, 0.20, 0.36, 0.00, 0.96, 1.56, 0.44, 1.50, 1.19, 0.93,
+            ]
+            .iter()
+            .map(|d| Sample { sample: *d })
+            .collect(),
+        );
+
+        let result = two_sample_test(&a, &b).unwrap();
+
+        assert!(f32::abs(result.statistic - 0.5667) < 0.0001);
+        assert!(f32::abs(result.effective_n - 5.4545) < 0.0001);
+        assert!(f32::abs(result.p_value - 0.0478) < 0.0001);
+    }
+
+    /// Identical experiments have a statistic of zero, even with ties
+    #[test]
+    fn two_sample_test_identical_works() {
+        let a: Experiment<u32> = Experiment::from_samples(
+            [1, 2, 2, 3, 5]
+                .iter()
+                .map(|d| Sample { sample: *d })
+                .collect(),
+        );
+        let b: Experiment<u32> = Experiment::from_samples(
+            [5, 3, 2, 2, 1]
+                .iter()
+                .map(|d| Sample { sample: *d })
+                .collect(),
+        );
+
+        let result = two_sample_test(&a, &b).unwrap();
+
+        assert_eq!(result.statistic, 0.0);
+        assert_eq!(result.p_value, 1.0);
+    }
+
+    /// 64-bit outputs are compared exactly, even when they differ by
+    /// less than an f64 can represent
+    #[test]
+    fn two_sample_test_u64_works() {
+        let a: Experiment<u64> = Experiment::from_samples(
+            (0..20)
+                .map(|i| Sample {
+                    sample: u64::MAX - 2 * i,
+                })
+                .collect(),
+        );
+        let b: Experiment<u64> = Experiment::from_samples(
+            (0..20)
+                .map(|i| Sample {
+                    sample: u64::MAX - 2 * i - 1,
+                })
+                .collect(),
+        );
+
+        let result = two_sample_test(&a, &b).unwrap();
+
+        assert_eq!(result.statistic, 0.05);
+        assert_eq!(result.effective_n, 10.0);
+    }
+
+    /// Empty experiments can't be compared
+    #[test]
+    fn two_sample_test_empty_fails() {
+        let a: Experiment<u32> = Experiment::from_samples(vec![]);
+        let b: Experiment<u32> = Experiment::from_samples(vec![Sample { sample: 1 }]);
+
+        assert!(two_sample_test(&a, &b).is_none());
+    }
 }
@@ -0,0 +1,205 @@
+//! Kuiper test
+//! Perform a Kuiper goodness of fit test on a data set
+//! This can be used to find if a set of values comes from a uniform
+//! distribution.
+//! Checksum outputs live on a ring modulo 2^n.  The
+//! Kolmogorov–Smirnov statistic depends on where that ring is cut,
+//! the Kuiper statistic doesn't.  Rotating every sample by the same
+//! amount, modulo the output space, gives the same result.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::{
+    distribution::{Cdf, DiscreteUniformDistributionParameters},
+    experiment::Experiment,
+};
+
+/// The result of a Kuiper test
+#[derive(Debug)]
+pub struct KuiperTest {
+    /// The Kuiper V statistic
+    pub statistic: f32,
+    /// The probability of seeing a statistic at least this large if
+    /// the data follows the expected distribution
+    pub p_value: f32,
+}
+
+/// Calculate the Kuiper V statistic
+///
+/// V = D+ + D-, the sum of the largest differences above and below
+/// the expected CDF.
+///
+/// The samples are normalized to [0, 1] with the parameters of the
+/// uniform distribution.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{
+///     distribution::DiscreteUniformDistributionParameters,
+///     experiment::Experiment,
+///     kuiper::statistic,
+///     sample::Sample,
+/// };
+///
+/// let data: [u32; 4] = [10, 30, 50, 70];
+///
+/// let samples: Vec<Sample<u32>> = data.iter().map(|d| Sample { sample: *d }).collect();
+///
+/// let experiment: Experiment<u32> = Experiment::from_samples(samples);
+/// let parameters = DiscreteUniformDistributionParameters { a: 0, b: 100 };
+/// let statistic = statistic(&experiment, &parameters);
+/// assert!(f32::abs(statistic - 0.4) < 0.0001);
+/// ```
+pub fn statistic(
+    experiment: &Experiment<u32>,
+    parameters: &DiscreteUniformDistributionParameters,
+) -> f32 {
+    let mut u: Vec<f64> = experiment
+        .samples
+        .iter()
+        .map(|s| parameters.cdf(s.sample as f32) as f64)
+        .collect();
+    u.sort_by(|a, b| a.total_cmp(b));
+
+    let n = u.len() as f64;
+    let mut plus_max: f64 = 0.0;
+    let mut minus_max: f64 = 0.0;
+
+    for (i, item) in u.iter().enumerate() {
+        plus_max = f64::max(plus_max, (i + 1) as f64 / n - item);
+        minus_max = f64::max(minus_max, item - i as f64 / n);
+    }
+
+    (plus_max + minus_max) as f32
+}
+
+/// Compute the p-value for a Kuiper statistic with n samples
+///
+/// This uses Stephens' approximation, which scales the statistic by
+/// sqrt(n) + 0.155 + 0.24 / sqrt(n) and uses the limiting
+/// distribution:
+/// Q(x) = 2 * sum_{j=1}^{inf} (4 j^2 x^2 - 1) e^(-2 j^2 x^2)
+///
+/// Stephens, M. A. (1970). Use of the Kolmogorov-Smirnov,
+/// Cramer-Von Mises and Related Statistics Without Extensive Tables.
+/// Journal of the Royal Statistical Society, Series B, 32(1), 115–122.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::kuiper::p_value;
+///
+/// let p = p_value(0.2, 100);
+/// assert!(f32::abs(p - 0.0078) < 0.0001);
+/// ```
+pub fn p_value(statistic: f32, n: u32) -> f32 {
+    if n == 0 {
+        return 1.0;
+    }
+
+    let rn = f64::sqrt(n as f64);
+    let x = (rn + 0.155 + 0.24 / rn) * statistic as f64;
+
+    // The series converges slowly for small x, and the tail
+    // probability is one to well within f32 precision there
+    if x < 0.4 {
+        return 1.0;
+    }
+
+    let mut sum = 0.0;
+    for j in 1..=100 {
+        let j2x2 = (j * j) as f64 * x * x;
+        let term = (4.0 * j2x2 - 1.0) * f64::exp(-2.0 * j2x2);
+        sum += term;
+        if f64::abs(term) < 1.0e-16 * f64::abs(sum) {
+            break;
+        }
+    }
+
+    (2.0 * sum).clamp(0.0, 1.0) as f32
+}
+
+/// Perform a Kuiper test on an experiment
+///
+/// Returns None if the experiment is empty.
+pub fn test(
+    experiment: &Experiment<u32>,
+    parameters: &DiscreteUniformDistributionParameters,
+) -> Option<KuiperTest> {
+    if experiment.samples.is_empty() {
+        return None;
+    }
+
+    let statistic = statistic(experiment, parameters);
+    let n: u32 = experiment.samples.len().try_into().ok()?;
+
+    Some(KuiperTest {
+        statistic,
+        p_value: p_value(statistic, n),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        distribution::DiscreteUniformDistributionParameters,
+        experiment::Experiment,
+        kuiper::{p_value, statistic, test},
+        sample::Sample,
+    };
+
+    /// PennState STAT 415 Kolmogorov–Smirnov example data, scaled to
+    /// integers
+    #[test]
+    fn statistic_works_psu() {
+        let data: [u32; 8] = [141, 26, 197, 33, 55, 77, 146, 118];
+
+        let samples: Vec<Sample<u32>> = data.iter().map(|d| Sample { sample: *d }).collect();
+
+        let experiment: Experiment<u32> = Experiment::from_samples(samples);
+        let parameters = DiscreteUniformDistributionParameters { a: 0, b: 200 };
+
+        let result = test(&experiment, &parameters).unwrap();
+        assert!(f32::abs(result.statistic - 0.275) < 0.0001);
+        assert!(f32::abs(result.p_value - 0.9598) < 0.0001);
+    }
+
+    /// Rotating the samples around the ring doesn't change the
+    /// statistic
+    #[test]
+    fn statistic_rotation_invariant() {
+        let data: [u32; 6] = [
+            0x1000_0000,
+            0x2000_0000,
+            0x2800_0000,
+            0x3000_0000,
+            0x9000_0000,
+            0xF000_0000,
+        ];
+        let parameters = DiscreteUniformDistributionParameters { a: 0, b: u32::MAX };
+
+        let experiment: Experiment<u32> =
+            Experiment::from_samples(data.iter().map(|d| Sample { sample: *d }).collect());
+        let original = statistic(&experiment, &parameters);
+
+        let rotated: Experiment<u32> = Experiment::from_samples(
+            data.iter()
+                .map(|d| Sample {
+                    sample: d.wrapping_add(0x8000_0000),
+                })
+                .collect(),
+        );
+        let rotated = statistic(&rotated, &parameters);
+
+        assert!(f32::abs(original - rotated) < 0.0001);
+    }
+
+    /// Test p-values against Stephens' approximation
+    #[test]
+    fn p_value_works() {
+        assert!(f32::abs(p_value(0.1, 100) - 0.7996) < 0.0001);
+        assert!(f32::abs(p_value(0.055_245, 1000) - 0.0475) < 0.0001);
+        assert_eq!(p_value(0.0, 100), 1.0);
+    }
+}
@@ -0,0 +1,525 @@
+//! Message generators for experiments
+//!
+//! Checksums that look fine on uniformly random messages can fail on
+//! structured data: text, packets that are mostly zero bytes, or
+//! repeated patterns.  A [`MessageSource`] decides the length and
+//! contents of each message an experiment checksums.
+//!
+//! Every source here takes its randomness from a
+//! [`Distribution<u32>`], so experiments can be replayed from the
+//! distribution's seed.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::distribution::Distribution;
+
+/// A generator of messages to checksum
+pub trait MessageSource {
+    /// The length of the next message in bytes
+    fn next_length(&mut self) -> usize;
+
+    /// Fill a message with generated bytes
+    ///
+    /// The message is already sized to the length returned by
+    /// [`MessageSource::next_length`].
+    fn fill(&mut self, message: &mut [u8]);
+
+    /// The seed of the source's distribution, if it has one
+    fn seed(&self) -> Option<u64> {
+        None
+    }
+
+    /// The seed of the distribution of message lengths, if the lengths
+    /// are random and it has one
+    fn length_seed(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Uniformly random bytes
+///
+/// Each byte is the top byte of a sample from the distribution.  This
+/// is the message source [`Experiment::run`] uses.
+///
+/// [`Experiment::run`]: crate::analysis::experiment::Experiment::run
+pub struct UniformMessages<D: Distribution<u32>> {
+    /// The distribution the bytes are taken from
+    pub prng: D,
+    /// The length of each message in bytes
+    pub length: usize,
+}
+
+impl<D: Distribution<u32>> UniformMessages<D> {
+    /// Create a new source of uniformly random messages
+    pub fn new(prng: D, length: usize) -> Self {
+        Self { prng, length }
+    }
+}
+
+impl<D: Distribution<u32>> MessageSource for UniformMessages<D> {
+    fn next_length(&mut self) -> usize {
+        self.length
+    }
+
+    fn fill(&mut self, message: &mut [u8]) {
+        for item in message {
+            *item = (self.prng.sample().sample >> 24) as u8;
+        }
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.prng.seed()
+    }
+}
+
+/// Printable ASCII text, bytes from 0x20 (space) to 0x7E (~)
+///
+/// # Examples
+/// ```
+/// use tapestry_analysis::analysis::{
+///     distribution::DiscreteUniformDistribution,
+///     message::{MessageSource, PrintableAsciiMessages},
+/// };
+///
+/// let dud = DiscreteUniformDistribution::new(0, u32::MAX);
+/// let mut source = PrintableAsciiMessages::new(dud, 16);
+/// let mut message = vec![0; source.next_length()];
+/// source.fill(&mut message);
+/// assert!(message.iter().all(|b| (0x20..=0x7E).contains(b)));
+/// ```
+pub struct PrintableAsciiMessages<D: Distribution<u32>> {
+    /// The distribution the characters are taken from
+    pub prng: D,
+    /// The length of each message in bytes
+    pub length: usize,
+}
+
+impl<D: Distribution<u32>> PrintableAsciiMessages<D> {
+    /// The number of printable ASCII characters
+    const NUM_PRINTABLE: u64 = 0x7F - 0x20;
+
+    /// Create a new source of printable ASCII messages
+    pub fn new(prng: D, length: usize) -> Self {
+        Self { prng, length }
+    }
+}
+
+impl<D: Distribution<u32>> MessageSource for PrintableAsciiMessages<D> {
+    fn next_length(&mut self) -> usize {
+        self.length
+    }
+
+    fn fill(&mut self, message: &mut [u8]) {
+        for item in message {
+            // Scale the sample into the printable range
+            let sample = self.prng.sample().sample as u64;
+            *item = 0x20 + ((sample * Self::NUM_PRINTABLE) >> 32) as u8;
+        }
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.prng.seed()
+    }
+}
+
+/// Low-entropy messages that are mostly zero bytes
+///
+/// Each byte is non-zero with probability density, and non-zero
+/// bytes are uniform over 1 to 255.  This models packets with a lot
+/// of padding or unused fields.
+pub struct SparseMessages<D: Distribution<u32>> {
+    /// The distribution the bytes are taken from
+    pub prng: D,
+    /// The length of each message in bytes
+    pub length: usize,
+    /// The probability of each byte being non-zero
+    pub density: f32,
+}
+
+impl<D: Distribution<u32>> SparseMessages<D> {
+    /// Create a new source of sparse messages
+    ///
+    /// # Panics
+    ///
+    /// Panics if density is not between zero and one.
+    pub fn new(prng: D, length: usize, density: f32) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&density),
+            "density must be between 0 and 1"
+        );
+
+        Self {
+            prng,
+            length,
+            density,
+        }
+    }
+}
+
+impl<D: Distribution<u32>> MessageSource for SparseMessages<D> {
+    fn next_length(&mut self) -> usize {
+        self.length
+    }
+
+    fn fill(&mut self, message: &mut [u8]) {
+        // Compare against the threshold in 32-bit fixed point
+        let threshold = (self.density as f64 * (1u64 << 32) as f64) as u64;
+
+        for item in message {
+            *item = if (self.prng.sample().sample as u64) < threshold {
+                let sample = self.prng.sample().sample as u64;
+                1 + ((sample * 255) >> 32) as u8
+            } else {
+                0
+            };
+        }
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.prng.seed()
+    }
+}
+
+/// Messages made of a short random pattern repeated to fill the
+/// message
+///
+/// A new pattern is generated for each message.  The last copy of the
+/// pattern is cut short if the length isn't a multiple of the period.
+pub struct RepeatedPatternMessages<D: Distribution<u32>> {
+    /// The distribution the pattern bytes are taken from
+    pub prng: D,
+    /// The length of each message in bytes
+    pub length: usize,
+    /// The length of the repeated pattern in bytes
+    pub period: usize,
+}
+
+impl<D: Distribution<u32>> RepeatedPatternMessages<D> {
+    /// Create a new source of repeated pattern messages
+    ///
+    /// # Panics
+    ///
+    /// Panics if period is zero.
+    pub fn new(prng: D, length: usize, period: usize) -> Self {
+        assert!(period > 0, "period must be at least one byte");
+
+        Self {
+            prng,
+            length,
+            period,
+        }
+    }
+}
+
+impl<D: Distribution<u32>> MessageSource for RepeatedPatternMessages<D> {
+    fn next_length(&mut self) -> usize {
+        self.length
+    }
+
+    fn fill(&mut self, message: &mut [u8]) {
+        let pattern: Vec<u8> = (0..self.period)
+            .map(|_| (self.prng.sample().sample >> 24) as u8)
+            .collect();
+
+        for (item, p) in message.iter_mut().zip(pattern.iter().cycle()) {
+            *item = *p;
+        }
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.prng.seed()
+    }
+}
+
+/// Messages with lengths drawn from a distribution
+///
+/// The contents come from another message source, so any source can
+/// be made variable length.
+///
+/// # Examples
+/// ```
+/// use tapestry_analysis::analysis::{
+///     distribution::DiscreteUniformDistribution,
+///     message::{MessageSource, UniformMessages, VariableLengthMessages},
+/// };
+///
+/// let bytes = UniformMessages::new(DiscreteUniformDistribution::new(0, u32::MAX), 0);
+/// let lengths = DiscreteUniformDistribution::new(1, 64);
+/// let mut source = VariableLengthMessages::new(bytes, lengths);
+/// let length = source.next_length();
+/// assert!((1..=64).contains(&length));
+/// ```
+pub struct VariableLengthMessages<S: MessageSource, L: Distribution<u32>> {
+    /// The source of the message contents
+    pub source: S,
+    /// The distribution of message lengths in bytes
+    pub lengths: L,
+}
+
+impl<S: MessageSource, L: Distribution<u32>> VariableLengthMessages<S, L> {
+    /// Create a new source of variable length messages
+    pub fn new(source: S, lengths: L) -> Self {
+        Self { source, lengths }
+    }
+}
+
+impl<S: MessageSource, L: Distribution<u32>> MessageSource for VariableLengthMessages<S, L> {
+    fn next_length(&mut self) -> usize {
+        self.lengths.sample().sample.try_into().unwrap()
+    }
+
+    fn fill(&mut self, message: &mut [u8]) {
+        self.source.fill(message);
+    }
+
+    /// The seed of the contents source
+    fn seed(&self) -> Option<u64> {
+        self.source.seed()
+    }
+
+    fn length_seed(&self) -> Option<u64> {
+        self.lengths.seed()
+    }
+}
+
+/// Every message of a range of lengths, in order
+///
+/// Messages of each length are generated in lexicographic order,
+/// starting with all zero bytes, before moving on to the next length.
+/// After the last message the source starts again from the first.
+///
+/// This doesn't use any randomness.  For lengths of one to four
+/// bytes every message can be checksummed, giving exact results
+/// instead of estimates.  The number of messages grows by a factor of
+/// 256 for every byte, four byte messages alone are 2^32 messages, so
+/// longer messages aren't supported.
+///
+/// # Examples
+/// ```
+/// use tapestry_analysis::analysis::message::{ExhaustiveMessages, MessageSource};
+///
+/// let mut source = ExhaustiveMessages::up_to(2);
+/// assert_eq!(source.num_messages(), 256 + 65536);
+///
+/// let mut message = vec![0; source.next_length()];
+/// source.fill(&mut message);
+/// assert_eq!(message, vec![0]);
+/// ```
+pub struct ExhaustiveMessages {
+    /// The shortest message length in bytes
+    pub min_length: usize,
+    /// The longest message length in bytes
+    pub max_length: usize,
+    /// The next message to generate
+    current: Vec<u8>,
+}
+
+impl ExhaustiveMessages {
+    /// The longest message length that can be enumerated, 2^32
+    /// messages of four bytes
+    pub const MAX_LENGTH: usize = 4;
+
+    /// Create a source of every message of the given length
+    ///
+    /// # Panics
+    ///
+    /// Panics if length is greater than [`ExhaustiveMessages::MAX_LENGTH`].
+    pub fn new(length: usize) -> Self {
+        Self::with_lengths(length, length)
+    }
+
+    /// Create a source of every non-empty message up to max_length
+    /// bytes long
+    ///
+    /// # Panics
+    ///
+    /// Panics if max_length is zero or greater than
+    /// [`ExhaustiveMessages::MAX_LENGTH`].
+    pub fn up_to(max_length: usize) -> Self {
+        Self::with_lengths(1, max_length)
+    }
+
+    /// Create a source of every message from min_length to max_length
+    /// bytes long
+    ///
+    /// # Panics
+    ///
+    /// Panics if min_length is greater than max_length, or max_length
+    /// is greater than [`ExhaustiveMessages::MAX_LENGTH`].
+    pub fn with_lengths(min_length: usize, max_length: usize) -> Self {
+        assert!(min_length <= max_length, "min_length must be <= max_length");
+        assert!(
+            max_length <= Self::MAX_LENGTH,
+            "max_length must be <= {}",
+            Self::MAX_LENGTH
+        );
+
+        Self {
+            min_length,
+            max_length,
+            current: vec![0; min_length],
+        }
+    }
+
+    /// The number of messages before the source starts again
+    pub fn num_messages(&self) -> u64 {
+        (self.min_length..=self.max_length)
+            .map(|length| 1u64 << (8 * length))
+            .sum()
+    }
+
+    /// Move on to the next message
+    fn advance(&mut self) {
+        // Count up with the last byte as the least significant digit
+        for byte in self.current.iter_mut().rev() {
+            let (next, overflow) = byte.overflowing_add(1);
+            *byte = next;
+            if !overflow {
+                return;
+            }
+        }
+
+        // Every message of this length has been generated
+        let length = if self.current.len() < self.max_length {
+            self.current.len() + 1
+        } else {
+            self.min_length
+        };
+        self.current = vec![0; length];
+    }
+}
+
+impl MessageSource for ExhaustiveMessages {
+    fn next_length(&mut self) -> usize {
+        self.current.len()
+    }
+
+    fn fill(&mut self, message: &mut [u8]) {
+        message.copy_from_slice(&self.current);
+        self.advance();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        distribution::DiscreteUniformDistribution,
+        message::{
+            ExhaustiveMessages, MessageSource, PrintableAsciiMessages, RepeatedPatternMessages,
+            SparseMessages, UniformMessages, VariableLengthMessages,
+        },
+        prng::Pcg32,
+    };
+
+    /// Generate the next message from a source
+    fn next_message(source: &mut dyn MessageSource) -> Vec<u8> {
+        let mut message = vec![0; source.next_length()];
+        source.fill(&mut message);
+
+        message
+    }
+
+    #[test]
+    fn uniform_messages_works() {
+        let mut source = UniformMessages::new(Pcg32::new(1), 1000);
+        let message = next_message(&mut source);
+
+        assert_eq!(message.len(), 1000);
+        assert!(message.iter().any(|b| *b >= 0x80));
+        assert!(message.iter().any(|b| *b < 0x20));
+        assert_eq!(source.seed(), Some(1));
+    }
+
+    #[test]
+    fn printable_ascii_messages_works() {
+        let mut source = PrintableAsciiMessages::new(Pcg32::new(2), 1000);
+        let message = next_message(&mut source);
+
+        assert!(message.iter().all(|b| (0x20..=0x7E).contains(b)));
+        assert!(message.contains(&0x20));
+        assert!(message.contains(&0x7E));
+    }
+
+    #[test]
+    fn sparse_messages_works() {
+        let mut source = SparseMessages::new(Pcg32::new(3), 10_000, 0.05);
+        let message = next_message(&mut source);
+
+        let non_zero = message.iter().filter(|b| **b != 0).count();
+        assert!((400..600).contains(&non_zero));
+
+        let mut source = SparseMessages::new(Pcg32::new(3), 100, 0.0);
+        assert!(next_message(&mut source).iter().all(|b| *b == 0));
+        let mut source = SparseMessages::new(Pcg32::new(3), 100, 1.0);
+        assert!(next_message(&mut source).iter().all(|b| *b != 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn sparse_messages_invalid_density_fails() {
+        SparseMessages::new(Pcg32::new(3), 100, 1.5);
+    }
+
+    #[test]
+    fn repeated_pattern_messages_works() {
+        let mut source = RepeatedPatternMessages::new(Pcg32::new(4), 10, 4);
+        let message = next_message(&mut source);
+
+        assert_eq!(message.len(), 10);
+        assert_eq!(message[0..4], message[4..8]);
+        assert_eq!(message[0..2], message[8..10]);
+
+        // Each message gets a new pattern
+        assert_ne!(message, next_message(&mut source));
+    }
+
+    #[test]
+    fn variable_length_messages_works() {
+        let bytes = UniformMessages::new(Pcg32::new(5), 0);
+        let lengths = DiscreteUniformDistribution::with_seed(10, 20, 5);
+        let mut source = VariableLengthMessages::new(bytes, lengths);
+
+        let mut seen = [false; 21];
+        for _ in 0..1000 {
+            let length = next_message(&mut source).len();
+            assert!((10..=20).contains(&length));
+            seen[length] = true;
+        }
+        assert!(seen[10..].iter().all(|s| *s));
+        assert_eq!(source.seed(), Some(5));
+        assert_eq!(source.length_seed(), Some(5));
+    }
+
+    #[test]
+    fn exhaustive_messages_works() {
+        let mut source = ExhaustiveMessages::with_lengths(0, 2);
+        assert_eq!(source.num_messages(), 1 + 256 + 65536);
+
+        let mut messages = Vec::new();
+        for _ in 0..source.num_messages() {
+            messages.push(next_message(&mut source));
+        }
+
+        assert_eq!(messages[0], Vec::<u8>::new());
+        assert_eq!(messages[1], vec![0]);
+        assert_eq!(messages[256], vec![255]);
+        assert_eq!(messages[257], vec![0, 0]);
+        assert_eq!(messages[258], vec![0, 1]);
+        assert_eq!(messages[513], vec![1, 0]);
+        assert_eq!(messages.last().unwrap(), &vec![255, 255]);
+
+        // Every message is generated once
+        messages.sort();
+        messages.dedup();
+        assert_eq!(messages.len(), 1 + 256 + 65536);
+
+        // Then the source starts again
+        assert_eq!(next_message(&mut source), Vec::<u8>::new());
+    }
+
+    #[test]
+    #[should_panic]
+    fn exhaustive_messages_too_long_fails() {
+        ExhaustiveMessages::new(5);
+    }
+}
@@ -17,10 +17,32 @@
 #![warn(missing_docs)]
 #![warn(unsafe_code)]
 
+pub mod anderson_darling;
+pub mod avalanche;
+pub mod bit_bias;
+pub mod bit_independence;
+pub mod burst;
+pub mod chi_square;
+pub mod coverage;
+pub mod cramer_von_mises;
+pub mod crc_forge;
+pub mod crc_polynomial;
+pub mod crc_solver;
 pub mod distribution;
+pub mod error_detection;
 pub mod experiment;
+pub mod gf2;
+pub mod hamming_distance;
 pub mod histogram;
 pub mod ks;
+pub mod kuiper;
+pub mod message;
+pub mod polynomial_search;
+pub mod prng;
 #[cfg(feature = "external-rand")]
 pub mod rand_distribution;
 pub mod sample;
+pub mod special;
+#[cfg(test)]
+mod test_support;
+pub mod watson;
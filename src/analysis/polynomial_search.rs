@@ -0,0 +1,355 @@
+//! Search for CRC polynomials
+//! Find the best generator polynomials of a given width for a data
+//! word length and Hamming distance, in the style of Koopman's
+//! searches.
+//!
+//! Every generator of the width with a +1 term is a candidate, so a
+//! 32-bit search has 2^31 of them.  A generator and its reciprocal
+//! detect the same errors, so only the smaller of each pair is
+//! checked.  Candidates are rejected as soon as an undetected error
+//! pattern with fewer bits than the target Hamming distance is found,
+//! and the rest are ranked by the number of undetected patterns of
+//! each weight, fewest low weight patterns first.
+//!
+//! The search state is all in [`PolynomialSearch`], which checks a
+//! batch of candidates at a time.  Report progress between batches,
+//! and save the state to resume a long search later.
+//!
+//! Koopman, P., & Chakravarty, T. (2004). Cyclic Redundancy Code (CRC)
+//! Polynomial Selection for Embedded Networks. International
+//! Conference on Dependable Systems and Networks.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::{
+    crc_polynomial::{bit_width, from_generator, Notation},
+    gf2::Polynomial,
+    hamming_distance::{count_weights, detects_all_below, MAX_WEIGHT},
+};
+use checksum_tapestry::crc::{BitOrder, CRCConfiguration};
+
+/// Which generator polynomials to consider
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Restriction {
+    /// Every polynomial with a +1 term
+    None,
+    /// Polynomials with x + 1 as a factor, which detect every odd
+    /// number of bit errors
+    DivisibleByXPlusOne,
+    /// Primitive polynomials, which have the longest period
+    Primitive,
+}
+
+/// A generator polynomial that meets the target Hamming distance
+#[derive(Debug, PartialEq)]
+pub struct Candidate {
+    /// The generator polynomial, including the x^width term
+    pub generator: Polynomial,
+    /// The polynomial in Koopman notation
+    pub koopman: u64,
+    /// The number of undetected error patterns of each weight at the
+    /// target data length, indexed by weight up to one more than the
+    /// target Hamming distance, or [`MAX_WEIGHT`]
+    pub weights: Vec<u64>,
+}
+
+impl Candidate {
+    /// The Hamming distance at the target data length, or None if it's
+    /// more than the largest weight counted
+    pub fn hamming_distance(&self) -> Option<u32> {
+        self.weights
+            .iter()
+            .position(|w| *w > 0)
+            .map(|weight| weight as u32)
+    }
+
+    /// A 32-bit CRC configuration with this generator, with no initial
+    /// value or final XOR, that can be used to compute checksums
+    ///
+    /// Returns None if checksum-tapestry doesn't support the width.
+    pub fn configuration<'a>(
+        &self,
+        name: &'a str,
+        bit_order: BitOrder,
+    ) -> Option<CRCConfiguration<'a, u32>> {
+        let width = bit_width(self.generator.degree()?)?;
+        let poly = from_generator(self.generator, width as u32, Notation::Normal) as u32;
+        let reflect_out = matches!(bit_order, BitOrder::LSBFirst);
+
+        Some(CRCConfiguration::<u32>::new(
+            name,
+            width,
+            bit_order,
+            poly,
+            reflect_out,
+            None,
+            None,
+        ))
+    }
+}
+
+/// The state of a search for CRC generator polynomials
+///
+/// The fields are public so the state can be saved and the search
+/// resumed from it.
+#[derive(Debug)]
+pub struct PolynomialSearch {
+    /// The width of the CRC, the degree of the generators
+    pub width: u32,
+    /// The length of the data words in bits
+    pub data_length: usize,
+    /// The smallest acceptable Hamming distance at data_length
+    pub target_hamming_distance: u32,
+    /// Which polynomials to consider
+    pub restriction: Restriction,
+    /// The number of candidates to keep
+    pub num_best: usize,
+    /// The index of the next candidate to check, from 0 to
+    /// 2^(width - 1)
+    ///
+    /// The candidate's generator is x^width + 1 plus the index shifted
+    /// up one bit.
+    pub next_candidate: u64,
+    /// The number of candidates checked so far, not counting skipped
+    /// reciprocals
+    pub checked: u64,
+    /// The best candidates found so far, best first, with ties in the
+    /// order they were found
+    pub best: Vec<Candidate>,
+}
+
+impl PolynomialSearch {
+    /// Start a search for generators of the given width that have at
+    /// least target_hamming_distance at data_length bits, keeping the
+    /// num_best best
+    ///
+    /// # Panics
+    ///
+    /// Panics if width isn't from 2 to 32, the data length is zero or
+    /// the target Hamming distance isn't from 2 to [`MAX_WEIGHT`] + 1.
+    pub fn new(
+        width: u32,
+        data_length: usize,
+        target_hamming_distance: u32,
+        restriction: Restriction,
+        num_best: usize,
+    ) -> PolynomialSearch {
+        assert!((2..=32).contains(&width), "width must be from 2 to 32");
+        assert!(data_length > 0, "data_length must be greater than zero");
+        assert!(
+            (2..=MAX_WEIGHT as u32 + 1).contains(&target_hamming_distance),
+            "target_hamming_distance must be from 2 to MAX_WEIGHT + 1"
+        );
+
+        PolynomialSearch {
+            width,
+            data_length,
+            target_hamming_distance,
+            restriction,
+            num_best,
+            next_candidate: 0,
+            checked: 0,
+            best: Vec::new(),
+        }
+    }
+
+    /// The number of candidates in the whole search
+    pub fn num_candidates(&self) -> u64 {
+        1 << (self.width - 1)
+    }
+
+    /// Returns true if every candidate has been checked
+    pub fn is_done(&self) -> bool {
+        self.next_candidate >= self.num_candidates()
+    }
+
+    /// The fraction of the search done, from zero to one
+    pub fn progress(&self) -> f64 {
+        self.next_candidate as f64 / self.num_candidates() as f64
+    }
+
+    /// Returns true if a generator meets the restriction
+    fn allowed(&self, generator: Polynomial) -> bool {
+        match self.restriction {
+            Restriction::None => true,
+            // G(1) is zero when there are an even number of terms
+            Restriction::DivisibleByXPlusOne => generator.weight() % 2 == 0,
+            Restriction::Primitive => generator.is_primitive(),
+        }
+    }
+
+    /// Check up to batch_size more candidates, returning the number
+    /// checked
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tapestry_analysis::analysis::polynomial_search::{PolynomialSearch, Restriction};
+    ///
+    /// // CRC-8/SMBUS, 0x83 in Koopman notation, has HD=4 up to 119 bits
+    /// let mut search = PolynomialSearch::new(8, 119, 4, Restriction::None, 5);
+    /// while !search.is_done() {
+    ///     search.run(32);
+    ///     println!("{:.0}% done", search.progress() * 100.0);
+    /// }
+    ///
+    /// assert!(search.best.iter().all(|c| c.hamming_distance() == Some(4)));
+    /// assert!(search.best.iter().any(|c| c.koopman == 0x83));
+    /// ```
+    pub fn run(&mut self, batch_size: u64) -> u64 {
+        let end = self
+            .next_candidate
+            .saturating_add(batch_size)
+            .min(self.num_candidates());
+        let start = self.next_candidate;
+        let target = self.target_hamming_distance as usize;
+
+        for index in start..end {
+            self.next_candidate = index + 1;
+            let generator = Polynomial((1 << self.width) | ((index as u128) << 1) | 1);
+
+            // The reciprocal has the same Hamming distances
+            let reciprocal = Polynomial(generator.0.reverse_bits() >> (127 - self.width));
+            if reciprocal < generator {
+                continue;
+            }
+            self.checked += 1;
+
+            if !self.allowed(generator) || !detects_all_below(generator, self.data_length, target) {
+                continue;
+            }
+
+            let weights = count_weights(generator, self.data_length, target + 1)
+                .expect("candidates have a +1 term");
+            self.insert(Candidate {
+                generator,
+                koopman: from_generator(generator, self.width, Notation::Koopman),
+                weights,
+            });
+        }
+
+        end - start
+    }
+
+    /// Add a candidate to the best list, keeping it in order
+    fn insert(&mut self, candidate: Candidate) {
+        let position = self
+            .best
+            .partition_point(|c| c.weights[2..] <= candidate.weights[2..]);
+        if position < self.num_best {
+            self.best.insert(position, candidate);
+            self.best.truncate(self.num_best);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        gf2::Polynomial,
+        hamming_distance::{compute_generator, MAX_WEIGHT},
+        polynomial_search::{Candidate, PolynomialSearch, Restriction},
+    };
+    use checksum_tapestry::{
+        crc::{BitOrder, CRC},
+        Checksum,
+    };
+
+    /// The best candidates really have the target Hamming distance and
+    /// are in order
+    #[test]
+    fn search_works() {
+        let mut search = PolynomialSearch::new(8, 64, 4, Restriction::None, 10);
+        assert_eq!(search.num_candidates(), 128);
+        search.run(u64::MAX);
+
+        assert!(search.is_done());
+        assert_eq!(search.progress(), 1.0);
+        assert_eq!(search.best.len(), 10);
+        for candidate in &search.best {
+            let hd = compute_generator(candidate.generator, 64, MAX_WEIGHT).unwrap();
+            assert!(hd.hamming_distance.unwrap_or(7) >= 4);
+            assert_eq!(hd.weights[..=5], candidate.weights[..]);
+            assert_eq!(candidate.generator.0, (candidate.koopman << 1 | 1) as u128);
+        }
+        for (a, b) in search.best.iter().zip(search.best.iter().skip(1)) {
+            assert!(a.weights[2..] <= b.weights[2..]);
+        }
+    }
+
+    /// Running in batches, and resuming from saved state, gives the
+    /// same result as running at once
+    #[test]
+    fn search_resumes() {
+        let mut all = PolynomialSearch::new(10, 40, 4, Restriction::DivisibleByXPlusOne, 5);
+        all.run(u64::MAX);
+
+        let mut batches = PolynomialSearch::new(10, 40, 4, Restriction::DivisibleByXPlusOne, 5);
+        assert_eq!(batches.run(100), 100);
+        assert_eq!(batches.progress(), 100.0 / 512.0);
+
+        let mut resumed = PolynomialSearch {
+            best: batches.best,
+            ..PolynomialSearch::new(10, 40, 4, Restriction::DivisibleByXPlusOne, 5)
+        };
+        resumed.next_candidate = batches.next_candidate;
+        resumed.checked = batches.checked;
+        while !resumed.is_done() {
+            resumed.run(37);
+        }
+
+        assert_eq!(resumed.best, all.best);
+        assert_eq!(resumed.checked, all.checked);
+        assert!(all.best.iter().all(|c| c.generator.weight() % 2 == 0));
+    }
+
+    #[test]
+    fn search_primitive_works() {
+        let mut search = PolynomialSearch::new(8, 16, 3, Restriction::Primitive, 100);
+        search.run(u64::MAX);
+
+        // There are 16 primitive polynomials of degree 8, in 8
+        // reciprocal pairs
+        assert_eq!(search.best.len(), 8);
+        assert!(search.best.iter().all(|c| c.generator.is_primitive()));
+    }
+
+    /// No 8-bit CRC has HD=6 at 64 bits
+    #[test]
+    fn search_finds_nothing() {
+        let mut search = PolynomialSearch::new(8, 64, 6, Restriction::None, 10);
+        search.run(u64::MAX);
+
+        assert!(search.best.is_empty());
+        assert_eq!(search.checked, 72);
+    }
+
+    /// A candidate can be used as a CRC
+    #[test]
+    fn candidate_configuration_works() {
+        let mut search = PolynomialSearch::new(8, 64, 4, Restriction::None, 1);
+        search.run(u64::MAX);
+        let candidate = &search.best[0];
+
+        let configuration = candidate
+            .configuration("CRC-8/FOUND", BitOrder::MSBFirst)
+            .unwrap();
+        assert_eq!(
+            Polynomial::from_configuration(&configuration),
+            candidate.generator
+        );
+        let mut crc = CRC::<u32>::new(configuration, true);
+        assert_ne!(crc.compute(b"123456789"), crc.compute(b"123456788"));
+
+        let search = PolynomialSearch::new(24, 64, 4, Restriction::None, 1);
+        let candidate = Candidate {
+            generator: Polynomial(0x186_4CFB),
+            koopman: 0xC3_267D,
+            weights: vec![],
+        };
+        assert!(candidate
+            .configuration("CRC-24", BitOrder::MSBFirst)
+            .is_none());
+        assert_eq!(search.num_candidates(), 1 << 23);
+    }
+}
@@ -0,0 +1,346 @@
+//! Small deterministic pseudorandom number generators
+//!
+//! These generators don't need any external crates.  They implement
+//! [`Distribution<u32>`] and generate values uniformly over the full
+//! 32-bit range, so any of them can be passed to
+//! [`Experiment::run`](crate::analysis::experiment::Experiment::run).
+//!
+//! The choice of generator affects the structure of the generated
+//! messages.  Running the same experiment with several generators
+//! shows whether a result comes from the checksum or from a weak
+//! generator.
+//!
+//! None of these generators are cryptographically secure.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::{distribution::Distribution, sample::Sample};
+
+/// The SplitMix64 increment, the golden ratio scaled to 64 bits
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Mix the bits of a 64-bit value, the SplitMix64 finalizer
+fn mix64(z: u64) -> u64 {
+    let z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    let z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// The SplitMix64 generator
+///
+/// This is a fast generator with a 64-bit state that passes BigCrush.
+/// It's also used to expand seeds for the other generators here.
+///
+/// Steele, G. L., Lea, D., & Flood, C. H. (2014).
+/// Fast Splittable Pseudorandom Number Generators.
+pub struct SplitMix64 {
+    /// The current state of the generator
+    pub state: u64,
+    /// The seed the generator was initialized with
+    seed: u64,
+}
+
+impl SplitMix64 {
+    /// Create a new SplitMix64 generator with the given seed
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed, seed }
+    }
+
+    /// Generate the next 64-bit value
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix64(self.state)
+    }
+}
+
+impl Distribution<u32> for SplitMix64 {
+    fn sample(&mut self) -> Sample<u32> {
+        Sample {
+            sample: (self.next_u64() >> 32) as u32,
+        }
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+}
+
+/// The xorshift64* generator
+///
+/// A xorshift generator with a multiplication on the output to hide
+/// the linear structure of the low bits.  Only the high 32 bits of
+/// each output are used.
+///
+/// Vigna, S. (2016). An experimental exploration of Marsaglia's
+/// xorshift generators, scrambled.
+/// ACM Transactions on Mathematical Software, 42(4).
+pub struct XorShift64Star {
+    /// The current state of the generator, never zero
+    pub state: u64,
+    /// The seed the generator was initialized with
+    seed: u64,
+}
+
+impl XorShift64Star {
+    /// Create a new xorshift64* generator with the given seed
+    ///
+    /// The seed is expanded with SplitMix64, because a xorshift state
+    /// of zero never changes.
+    pub fn new(seed: u64) -> Self {
+        let mut state = mix64(seed.wrapping_add(GOLDEN_GAMMA));
+        if state == 0 {
+            state = GOLDEN_GAMMA;
+        }
+
+        Self { state, seed }
+    }
+
+    /// Generate the next 64-bit value
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+impl Distribution<u32> for XorShift64Star {
+    fn sample(&mut self) -> Sample<u32> {
+        Sample {
+            sample: (self.next_u64() >> 32) as u32,
+        }
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+}
+
+/// The PCG32 generator, PCG-XSH-RR with 64 bits of state
+///
+/// A linear congruential generator with a permutation on the output.
+///
+/// O'Neill, M. E. (2014). PCG: A Family of Simple Fast
+/// Space-Efficient Statistically Good Algorithms for Random Number
+/// Generation.
+pub struct Pcg32 {
+    /// The current state of the generator
+    pub state: u64,
+    /// The increment of the generator, which selects the stream.
+    /// Always odd.
+    pub increment: u64,
+    /// The seed the generator was initialized with
+    seed: u64,
+}
+
+impl Pcg32 {
+    /// The stream used by [`Pcg32::new`]
+    pub const DEFAULT_STREAM: u64 = 0xDA3E_39CB_94B9_5BDB;
+
+    /// Create a new PCG32 generator with the given seed on the default
+    /// stream
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, Self::DEFAULT_STREAM)
+    }
+
+    /// Create a new PCG32 generator with the given seed and stream
+    ///
+    /// Generators with the same seed on different streams produce
+    /// different sequences.  This follows pcg32_srandom_r from the
+    /// reference implementation.
+    ///
+    /// # Examples
+    /// ```
+    /// use tapestry_analysis::analysis::{distribution::Distribution, prng::Pcg32};
+    ///
+    /// // The first value from the reference implementation's demo
+    /// let mut pcg = Pcg32::with_stream(42, 54);
+    /// assert_eq!(pcg.sample().sample, 0xA15C02B7);
+    /// ```
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut pcg = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+            seed,
+        };
+        pcg.next_u32();
+        pcg.state = pcg.state.wrapping_add(seed);
+        pcg.next_u32();
+
+        pcg
+    }
+
+    /// Generate the next 32-bit value
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(self.increment);
+
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rotation = (old >> 59) as u32;
+        xorshifted.rotate_right(rotation)
+    }
+}
+
+impl Distribution<u32> for Pcg32 {
+    fn sample(&mut self) -> Sample<u32> {
+        Sample {
+            sample: self.next_u32(),
+        }
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+}
+
+/// A 32-bit Galois linear feedback shift register
+///
+/// This uses the maximal-length polynomial x^32 + x^22 + x^2 + x + 1,
+/// so the state cycles through every non-zero value.  Each sample
+/// clocks the register 32 times and collects the output bits.
+///
+/// LFSRs are a weak generator.  Their output is linear over GF(2),
+/// the same as a CRC, so they are useful for checking whether a
+/// result only shows up with a structured message source.
+pub struct Lfsr32 {
+    /// The current state of the register, never zero
+    pub state: u32,
+    /// The seed the generator was initialized with
+    seed: u64,
+}
+
+impl Lfsr32 {
+    /// The feedback taps for x^32 + x^22 + x^2 + x + 1 in a
+    /// right-shifting Galois register
+    pub const TAPS: u32 = 0x8020_0003;
+
+    /// Create a new LFSR with the given seed
+    ///
+    /// The seed is folded to 32 bits.  A register state of zero never
+    /// changes, so a seed that folds to zero is replaced with one.
+    pub fn new(seed: u64) -> Self {
+        let mut state = (seed ^ (seed >> 32)) as u32;
+        if state == 0 {
+            state = 1;
+        }
+
+        Self { state, seed }
+    }
+
+    /// Clock the register once, returning the output bit
+    pub fn next_bit(&mut self) -> u32 {
+        let bit = self.state & 1;
+        self.state >>= 1;
+        if bit == 1 {
+            self.state ^= Self::TAPS;
+        }
+
+        bit
+    }
+}
+
+impl Distribution<u32> for Lfsr32 {
+    fn sample(&mut self) -> Sample<u32> {
+        let mut value: u32 = 0;
+        for _ in 0..32 {
+            value = (value << 1) | self.next_bit();
+        }
+
+        Sample { sample: value }
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        chi_square::{test, uniform_probabilities},
+        distribution::Distribution,
+        experiment::Experiment,
+        histogram::{Histogram, SimpleHistogram},
+        prng::{Lfsr32, Pcg32, SplitMix64, XorShift64Star},
+    };
+
+    /// Bin a generator's output and run a chi-square test on it
+    fn chi_square_p_value(prng: &mut dyn Distribution<u32>) -> f32 {
+        let samples = (0..10_000).map(|_| prng.sample()).collect();
+        let experiment = Experiment::from_samples(samples);
+        let histogram = SimpleHistogram::new(&experiment, 16);
+
+        test(&histogram, &uniform_probabilities(16))
+            .unwrap()
+            .p_value
+    }
+
+    /// Test PCG32 against the output of the reference implementation's
+    /// pcg32-demo with seed 42 and stream 54
+    #[test]
+    fn pcg32_works() {
+        let mut pcg = Pcg32::with_stream(42, 54);
+        let expected: [u32; 6] = [
+            0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e,
+        ];
+
+        for e in expected {
+            assert_eq!(pcg.sample().sample, e);
+        }
+        assert_eq!(pcg.seed(), Some(42));
+    }
+
+    /// Test SplitMix64 against the reference implementation seeded
+    /// with zero
+    #[test]
+    fn splitmix64_works() {
+        let mut splitmix = SplitMix64::new(0);
+
+        assert_eq!(splitmix.next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(splitmix.next_u64(), 0x6E78_9E6A_A1B9_65F4);
+    }
+
+    /// The LFSR never gets stuck at zero
+    #[test]
+    fn lfsr_zero_seed_works() {
+        let mut lfsr = Lfsr32::new(0);
+
+        assert_ne!(lfsr.state, 0);
+        let first = lfsr.sample().sample;
+        let second = lfsr.sample().sample;
+        assert_ne!(first, second);
+    }
+
+    /// Check two generators created with the same seed produce the
+    /// same values
+    fn assert_reproducible(first: &mut dyn Distribution<u32>, second: &mut dyn Distribution<u32>) {
+        for _ in 0..100 {
+            assert_eq!(first.sample().sample, second.sample().sample);
+        }
+        assert_eq!(first.seed(), second.seed());
+    }
+
+    /// Every generator is reproducible from its seed
+    #[test]
+    fn generators_are_reproducible() {
+        assert_reproducible(&mut SplitMix64::new(5), &mut SplitMix64::new(5));
+        assert_reproducible(&mut XorShift64Star::new(5), &mut XorShift64Star::new(5));
+        assert_reproducible(&mut Pcg32::new(5), &mut Pcg32::new(5));
+        assert_reproducible(&mut Lfsr32::new(5), &mut Lfsr32::new(5));
+        assert_eq!(Lfsr32::new(5).seed(), Some(5));
+    }
+
+    /// Every generator passes a simple uniformity test
+    #[test]
+    fn generators_are_uniform() {
+        assert!(chi_square_p_value(&mut SplitMix64::new(1)) > 0.01);
+        assert!(chi_square_p_value(&mut XorShift64Star::new(1)) > 0.01);
+        assert!(chi_square_p_value(&mut Pcg32::new(1)) > 0.01);
+        assert!(chi_square_p_value(&mut Lfsr32::new(1)) > 0.01);
+    }
+}
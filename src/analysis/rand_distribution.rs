@@ -1,5 +1,5 @@
 //! A set of random distribution implementations using the rand crate
-use rand::{rngs::ThreadRng, Rng};
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
 
 use crate::analysis::{distribution::Distribution, sample::Sample};
 
@@ -15,7 +15,10 @@ pub struct RandDiscreteUniformDistribution {
     pub b: u32,
 
     /// The current state of the random number generator
-    pub state: ThreadRng,
+    pub state: StdRng,
+
+    /// The seed the random number generator was initialized with
+    seed: u64,
 }
 
 impl Distribution<u32> for RandDiscreteUniformDistribution {
@@ -24,14 +27,125 @@ impl Distribution<u32> for RandDiscreteUniformDistribution {
             sample: self.state.gen_range(self.a..=self.b),
         }
     }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
 }
 
 impl RandDiscreteUniformDistribution {
     /// Create a new RandDiscreteUniformDistribution with the given
     /// parameters
+    ///
+    /// The seed is taken from the thread-local random number
+    /// generator.  Use [`RandDiscreteUniformDistribution::with_seed`]
+    /// for reproducible results.
     pub fn new(a: u32, b: u32) -> Self {
-        let rng = rand::thread_rng();
+        let seed: u64 = rand::thread_rng().gen();
+
+        Self::with_seed(a, b, seed)
+    }
+
+    /// Create a new RandDiscreteUniformDistribution with the given
+    /// parameters and seed
+    ///
+    /// Two distributions created with the same seed generate the same
+    /// sequence of samples.
+    pub fn with_seed(a: u32, b: u32, seed: u64) -> Self {
+        let rng = StdRng::seed_from_u64(seed);
+
+        Self {
+            a,
+            b,
+            state: rng,
+            seed,
+        }
+    }
+}
 
-        Self { a, b, state: rng }
+/// An adapter that lets any rand RngCore be used as a Distribution
+///
+/// Samples are taken from next_u32, so they cover the full 32-bit
+/// range.
+///
+/// # Examples
+/// ```
+/// use rand::{rngs::StdRng, SeedableRng};
+/// use tapestry_analysis::analysis::{
+///     distribution::Distribution, rand_distribution::RngCoreDistribution,
+/// };
+///
+/// let mut distribution = RngCoreDistribution::new(StdRng::seed_from_u64(1));
+/// let _sample = distribution.sample();
+/// ```
+pub struct RngCoreDistribution<R: RngCore> {
+    /// The wrapped random number generator
+    pub rng: R,
+    /// The seed the generator was created with, if known
+    seed: Option<u64>,
+}
+
+impl<R: RngCore> RngCoreDistribution<R> {
+    /// Wrap a random number generator
+    pub fn new(rng: R) -> Self {
+        Self { rng, seed: None }
+    }
+
+    /// Wrap a random number generator and record the seed it was
+    /// created with, so experiments run with it can be replayed
+    pub fn with_seed(rng: R, seed: u64) -> Self {
+        Self {
+            rng,
+            seed: Some(seed),
+        }
+    }
+}
+
+impl<R: RngCore> Distribution<u32> for RngCoreDistribution<R> {
+    fn sample(&mut self) -> Sample<u32> {
+        Sample {
+            sample: self.rng.next_u32(),
+        }
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    use crate::analysis::{
+        distribution::Distribution,
+        rand_distribution::{RandDiscreteUniformDistribution, RngCoreDistribution},
+    };
+
+    /// Distributions with the same seed generate the same samples
+    #[test]
+    fn with_seed_is_reproducible() {
+        let mut first = RandDiscreteUniformDistribution::with_seed(0, 1000, 7);
+        let mut second = RandDiscreteUniformDistribution::with_seed(0, 1000, 7);
+
+        for _ in 0..100 {
+            let sample = first.sample().sample;
+            assert_eq!(sample, second.sample().sample);
+            assert!(sample <= 1000);
+        }
+        assert_eq!(first.seed(), Some(7));
+    }
+
+    /// The adapter passes through the wrapped generator's output
+    #[test]
+    fn rng_core_distribution_works() {
+        let mut distribution = RngCoreDistribution::with_seed(StdRng::seed_from_u64(3), 3);
+        let mut rng = StdRng::seed_from_u64(3);
+
+        for _ in 0..10 {
+            assert_eq!(distribution.sample().sample, rng.next_u32());
+        }
+        assert_eq!(distribution.seed(), Some(3));
+        assert_eq!(RngCoreDistribution::new(rng).seed(), None);
     }
 }
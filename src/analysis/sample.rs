@@ -64,6 +64,8 @@ impl<T> Samples<T> {
     }
 }
 
+// The comparisons follow IEEE 754, unlike the total ordering in Ord
+#[allow(clippy::non_canonical_partial_ord_impl)]
 impl PartialOrd for Sample<f32> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         self.sample.partial_cmp(&other.sample)
@@ -205,19 +207,19 @@ mod tests {
         );
 
         assert!(sample_1 < sample_3);
-        assert!(!(sample_1 < sample_2));
-        assert!(!(sample_3 < sample_1));
+        assert!(!sample_1.lt(&sample_2));
+        assert!(!sample_3.lt(&sample_1));
 
         assert!(sample_1 <= sample_2);
         assert!(sample_1 <= sample_3);
-        assert!(!(sample_3 <= sample_1));
+        assert!(!sample_3.le(&sample_1));
 
         assert!(sample_3 > sample_1);
-        assert!(!(sample_1 > sample_2));
-        assert!(!(sample_1 > sample_3));
+        assert!(!sample_1.gt(&sample_2));
+        assert!(!sample_1.gt(&sample_3));
 
         assert!(sample_1 >= sample_2);
         assert!(sample_3 >= sample_1);
-        assert!(!(sample_1 >= sample_3));
+        assert!(!sample_1.ge(&sample_3));
     }
 }
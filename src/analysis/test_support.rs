@@ -0,0 +1,51 @@
+//! Helpers shared by the unit tests
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use checksum_tapestry::Checksum;
+
+/// A 64-bit FNV-1a hash followed by the SplitMix64 finalizer
+///
+/// checksum-tapestry doesn't provide a 64-bit checksum, and this is
+/// close to an ideal function for avalanche and bit independence
+/// purposes.
+pub(crate) struct Mixed {
+    state: u64,
+}
+
+impl Mixed {
+    const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01B3;
+}
+
+impl Default for Mixed {
+    fn default() -> Self {
+        Mixed {
+            state: Self::OFFSET_BASIS,
+        }
+    }
+}
+
+impl Checksum<u64> for Mixed {
+    fn compute(&mut self, data: &[u8]) -> u64 {
+        self.reset();
+        for byte in data {
+            self.update(*byte);
+        }
+
+        let z = self.state;
+        let z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        let z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn update(&mut self, data: u8) -> u64 {
+        self.state = (self.state ^ data as u64).wrapping_mul(Self::PRIME);
+
+        self.state
+    }
+
+    fn reset(&mut self) {
+        self.state = Self::OFFSET_BASIS;
+    }
+}
@@ -0,0 +1,201 @@
+//! Watson test
+//! Perform a Watson U^2 goodness of fit test on a data set
+//! This can be used to find if a set of values comes from a uniform
+//! distribution.
+//! Watson's U^2 is the circular version of the Cramér–von Mises
+//! statistic.  Like the Kuiper statistic, it doesn't depend on where
+//! the ring of checksum outputs is cut.
+#![warn(missing_docs)]
+#![warn(unsafe_code)]
+
+use crate::analysis::{
+    distribution::{Cdf, DiscreteUniformDistributionParameters},
+    experiment::Experiment,
+};
+
+/// The result of a Watson test
+#[derive(Debug)]
+pub struct WatsonTest {
+    /// The Watson U^2 statistic
+    pub statistic: f32,
+    /// The probability of seeing a statistic at least this large if
+    /// the data follows the expected distribution
+    pub p_value: f32,
+}
+
+/// Calculate the Watson U^2 statistic
+///
+/// U^2 = W^2 - n (mean(u) - 1/2)^2, where W^2 is the Cramér–von Mises
+/// statistic and u are the normalized samples.
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::{
+///     distribution::DiscreteUniformDistributionParameters,
+///     experiment::Experiment,
+///     sample::Sample,
+///     watson::statistic,
+/// };
+///
+/// let data: [u32; 8] = [141, 26, 197, 33, 55, 77, 146, 118];
+///
+/// let samples: Vec<Sample<u32>> = data.iter().map(|d| Sample { sample: *d }).collect();
+///
+/// let experiment: Experiment<u32> = Experiment::from_samples(samples);
+/// let parameters = DiscreteUniformDistributionParameters { a: 0, b: 200 };
+/// let statistic = statistic(&experiment, &parameters);
+/// assert!(f32::abs(statistic - 0.02961) < 0.00001);
+/// ```
+pub fn statistic(
+    experiment: &Experiment<u32>,
+    parameters: &DiscreteUniformDistributionParameters,
+) -> f32 {
+    let mut u: Vec<f64> = experiment
+        .samples
+        .iter()
+        .map(|s| parameters.cdf(s.sample as f32) as f64)
+        .collect();
+    u.sort_by(|a, b| a.total_cmp(b));
+
+    let n = u.len() as f64;
+    let mut w2: f64 = 1.0 / (12.0 * n);
+    for (i, item) in u.iter().enumerate() {
+        let difference = item - (2 * i + 1) as f64 / (2.0 * n);
+        w2 += difference * difference;
+    }
+
+    let mean = u.iter().sum::<f64>() / n;
+
+    (w2 - n * (mean - 0.5) * (mean - 0.5)) as f32
+}
+
+/// Compute the p-value for a Watson statistic with n samples
+///
+/// The statistic is adjusted with Stephens' modification
+/// U*^2 = (U^2 - 0.1/n + 0.1/n^2)(1 + 0.8/n), and compared to the
+/// limiting distribution:
+/// Q(u) = 2 * sum_{k=1}^{inf} (-1)^(k-1) e^(-2 k^2 pi^2 u)
+///
+/// # Examples
+///
+/// ```
+/// use tapestry_analysis::analysis::watson::p_value;
+///
+/// // The asymptotic five percent point is 0.187
+/// let p = p_value(0.187, 1000);
+/// assert!(f32::abs(p - 0.05) < 0.001);
+/// ```
+pub fn p_value(statistic: f32, n: u32) -> f32 {
+    if n == 0 {
+        return 1.0;
+    }
+
+    let n = n as f64;
+    let modified = (statistic as f64 - 0.1 / n + 0.1 / (n * n)) * (1.0 + 0.8 / n);
+
+    // The alternating series converges slowly for small values, and
+    // the tail probability is one to well within f32 precision there
+    if modified < 0.01 {
+        return 1.0;
+    }
+
+    let pi2 = std::f64::consts::PI * std::f64::consts::PI;
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for k in 1..=100 {
+        let k = k as f64;
+        let term = f64::exp(-2.0 * k * k * pi2 * modified);
+        sum += sign * term;
+        if term < 1.0e-16 * f64::abs(sum) {
+            break;
+        }
+        sign = -sign;
+    }
+
+    (2.0 * sum).clamp(0.0, 1.0) as f32
+}
+
+/// Perform a Watson test on an experiment
+///
+/// Returns None if the experiment is empty.
+pub fn test(
+    experiment: &Experiment<u32>,
+    parameters: &DiscreteUniformDistributionParameters,
+) -> Option<WatsonTest> {
+    if experiment.samples.is_empty() {
+        return None;
+    }
+
+    let statistic = statistic(experiment, parameters);
+    let n: u32 = experiment.samples.len().try_into().ok()?;
+
+    Some(WatsonTest {
+        statistic,
+        p_value: p_value(statistic, n),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{
+        distribution::DiscreteUniformDistributionParameters,
+        experiment::Experiment,
+        sample::Sample,
+        watson::{p_value, statistic, test},
+    };
+
+    /// PennState STAT 415 Kolmogorov–Smirnov example data, scaled to
+    /// integers
+    #[test]
+    fn statistic_works_psu() {
+        let data: [u32; 8] = [141, 26, 197, 33, 55, 77, 146, 118];
+
+        let samples: Vec<Sample<u32>> = data.iter().map(|d| Sample { sample: *d }).collect();
+
+        let experiment: Experiment<u32> = Experiment::from_samples(samples);
+        let parameters = DiscreteUniformDistributionParameters { a: 0, b: 200 };
+
+        let result = test(&experiment, &parameters).unwrap();
+        assert!(f32::abs(result.statistic - 0.02961) < 0.00001);
+        assert!(f32::abs(result.p_value - 0.9873) < 0.0001);
+    }
+
+    /// Rotating the samples around the ring doesn't change the
+    /// statistic
+    #[test]
+    fn statistic_rotation_invariant() {
+        let data: [u32; 6] = [
+            0x1000_0000,
+            0x2000_0000,
+            0x2800_0000,
+            0x3000_0000,
+            0x9000_0000,
+            0xF000_0000,
+        ];
+        let parameters = DiscreteUniformDistributionParameters { a: 0, b: u32::MAX };
+
+        let experiment: Experiment<u32> =
+            Experiment::from_samples(data.iter().map(|d| Sample { sample: *d }).collect());
+        let original = statistic(&experiment, &parameters);
+
+        let rotated: Experiment<u32> = Experiment::from_samples(
+            data.iter()
+                .map(|d| Sample {
+                    sample: d.wrapping_add(0x8000_0000),
+                })
+                .collect(),
+        );
+        let rotated = statistic(&rotated, &parameters);
+
+        assert!(f32::abs(original - rotated) < 0.0001);
+    }
+
+    /// Test p-values against Stephens' approximation
+    #[test]
+    fn p_value_works() {
+        assert!(f32::abs(p_value(0.1, 20) - 0.2822) < 0.0001);
+        assert!(f32::abs(p_value(0.3, 50) - 0.005_07) < 0.00001);
+        assert_eq!(p_value(0.0, 20), 1.0);
+    }
+}